@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+
+use crate::GameState;
+
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(GameState::Game).with_system(animation_system));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AnimationMode {
+    Loop,
+    // stops on the last frame
+    Once,
+    // despawns the entity after the last frame
+    DespawnWhenDone,
+}
+
+// a run of frames in a texture atlas
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AnimationClip {
+    pub first: usize,
+    pub frames: usize,
+    pub frame_time: f32,
+    pub mode: AnimationMode,
+}
+
+impl AnimationClip {
+    pub const fn new(first: usize, frames: usize, frame_time: f32, mode: AnimationMode) -> Self {
+        Self { first, frames, frame_time, mode }
+    }
+}
+
+// steps the TextureAtlasSprite on the same entity through a clip
+#[derive(Component)]
+pub struct Animation {
+    clip: AnimationClip,
+    frame: usize,
+    timer: Timer,
+    finished: bool,
+}
+
+impl Animation {
+    pub fn new(clip: AnimationClip) -> Self {
+        Self {
+            clip,
+            frame: 0,
+            timer: Timer::from_seconds(clip.frame_time, TimerMode::Repeating),
+            finished: false,
+        }
+    }
+
+    pub fn clip(&self) -> AnimationClip {
+        self.clip
+    }
+
+    // switches to another clip from its first frame, playing the same clip again does nothing
+    pub fn play(&mut self, clip: AnimationClip) {
+        if self.clip != clip {
+            *self = Animation::new(clip);
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn index(&self) -> usize {
+        self.clip.first + self.frame
+    }
+}
+
+fn animation_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Animation, &mut TextureAtlasSprite)>,
+) {
+    for (entity, mut animation, mut sprite) in query.iter_mut() {
+        if !animation.finished {
+            animation.timer.tick(time.delta());
+
+            for _ in 0..animation.timer.times_finished_this_tick() {
+                if animation.frame + 1 < animation.clip.frames {
+                    animation.frame += 1;
+                    continue;
+                }
+
+                match animation.clip.mode {
+                    AnimationMode::Loop => animation.frame = 0,
+                    AnimationMode::Once => animation.finished = true,
+                    AnimationMode::DespawnWhenDone => {
+                        animation.finished = true;
+                        commands.entity(entity).despawn();
+                    },
+                }
+
+                if animation.finished {
+                    break;
+                }
+            }
+        }
+
+        // the clip may have just been switched, so this is set even without a new frame
+        let index = animation.index();
+        if sprite.index != index {
+            sprite.index = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::HeadlessApp;
+
+    #[test]
+    fn clips_loop_stop_or_despawn_at_the_end() {
+        let mut game = HeadlessApp::new("\
+###
+#P#
+###");
+
+        let clips = [
+            AnimationClip::new(2, 3, 0.1, AnimationMode::Loop),
+            AnimationClip::new(2, 3, 0.1, AnimationMode::Once),
+            AnimationClip::new(2, 3, 0.1, AnimationMode::DespawnWhenDone),
+        ];
+        let entities = clips.map(|clip| {
+            game.world().spawn((TextureAtlasSprite::default(), Animation::new(clip))).id()
+        });
+
+        // 0.4 seconds in, a frame past the end of the clip
+        game.run(25);
+
+        let world = game.world();
+        assert_eq!(world.get::<TextureAtlasSprite>(entities[0]).unwrap().index, 3);
+        assert_eq!(world.get::<TextureAtlasSprite>(entities[1]).unwrap().index, 4);
+        assert!(world.get::<Animation>(entities[1]).unwrap().finished());
+        assert!(world.get_entity(entities[2]).is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    GameState, TILE_SIZE, WinSize,
+    config::GameConfig,
+    events::GameplayEvent,
+    movement::MovementIntegration,
+    player::Player,
+    tilemap::Level,
+};
+
+// how far the view can shake at full trauma, and how fast trauma wears off per second
+const SHAKE_DISTANCE: f32 = 12.;
+const SHAKE_DECAY: f32 = 1.5;
+// trauma added by each explosion, it tops out at 1
+const EXPLOSION_TRAUMA: f32 = 0.5;
+
+// room kept around the players when the view zooms out to fit them all
+const FRAME_MARGIN: f32 = TILE_SIZE * 3.;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<CameraRig>()
+            .add_system_set(SystemSet::on_enter(GameState::Game).with_system(reset_camera_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Game)
+                    .with_system(camera_follow_system.after(MovementIntegration))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Game).with_system(reset_camera_system));
+    }
+}
+
+// where the camera is looking before shake, kept apart from the transform so the shake
+// never feeds back into the smoothing
+#[derive(Resource, Default)]
+pub struct CameraRig {
+    // None until the first frame of a level, which snaps straight to the players
+    focus: Option<Vec2>,
+    zoom: f32,
+    // 0 to 1, the shake is trauma squared so small knocks stay subtle
+    trauma: f32,
+}
+
+fn reset_camera_system(mut rig: ResMut<CameraRig>) {
+    *rig = CameraRig::default();
+}
+
+// moves a camera centre on one axis so the view stays inside the map, or puts it in the
+// middle of the map when the map is smaller than the view
+fn clamp_axis(centre: f32, view: f32, map_min: f32, map_max: f32) -> f32 {
+    if map_max - map_min <= view {
+        (map_min + map_max) / 2.
+    } else {
+        centre.clamp(map_min + view / 2., map_max - view / 2.)
+    }
+}
+
+// the point the camera wants to look at moves it only once it leaves the dead zone
+fn apply_dead_zone(focus: Vec2, target: Vec2, dead_zone: f32) -> Vec2 {
+    let offset = target - focus;
+    let outside = offset - offset.clamp(Vec2::splat(-dead_zone), Vec2::splat(dead_zone));
+    focus + outside
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn camera_follow_system(
+    time: Res<Time>,
+    level: Res<Level>,
+    config: Res<GameConfig>,
+    win_size: Res<WinSize>,
+    mut rig: ResMut<CameraRig>,
+    mut events: EventReader<GameplayEvent>,
+    player_query: Query<&Transform, With<Player>>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), (With<Camera>, Without<Player>)>,
+) {
+    let Ok((mut camera_transform, mut projection)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    for event in events.iter() {
+        if let GameplayEvent::Explosion { .. } = event {
+            rig.trauma = (rig.trauma + EXPLOSION_TRAUMA).min(1.);
+        }
+    }
+
+    // frame every player, zooming out when they're too far apart to fit
+    let mut players = player_query.iter().map(|transform| transform.translation.truncate());
+    let Some(first) = players.next() else {
+        return;
+    };
+    let (low, high) = players.fold((first, first), |(low, high), position| (low.min(position), high.max(position)));
+
+    // the world area the window shows before any zooming out
+    let pixel_scale = win_size.pixel_scale();
+    let window = win_size.size() / pixel_scale;
+    let spread = high - low + Vec2::splat(FRAME_MARGIN * 2.);
+    let target_zoom = (spread / window).max_element().max(1.);
+
+    let target = (low + high) / 2.;
+    let delta = time.delta_seconds();
+    // frame rate independent exponential smoothing
+    let blend = 1. - (-config.camera_damping * delta).exp();
+
+    let (focus, zoom) = match rig.focus {
+        None => (target, target_zoom),
+        Some(focus) => {
+            let desired = apply_dead_zone(focus, target, config.camera_dead_zone * TILE_SIZE);
+            (focus.lerp(desired, blend), rig.zoom + (target_zoom - rig.zoom) * blend)
+        },
+    };
+
+    // keep the view on the map, tiles are centred on their positions
+    let view = window * zoom;
+    let half = TILE_SIZE / 2.;
+    let focus = Vec2::new(
+        clamp_axis(focus.x, view.x, -half, level.width() as f32 * TILE_SIZE - half),
+        clamp_axis(focus.y, view.y, -(level.height() as f32) * TILE_SIZE + half, half),
+    );
+    rig.focus = Some(focus);
+    rig.zoom = zoom;
+
+    let shake = if rig.trauma > 0. {
+        let mut rng = rand::thread_rng();
+        let strength = rig.trauma * rig.trauma * SHAKE_DISTANCE;
+        rig.trauma = (rig.trauma - SHAKE_DECAY * delta).max(0.);
+        Vec2::new(rng.gen_range(-1. ..=1.), rng.gen_range(-1. ..=1.)) * strength
+    } else {
+        Vec2::ZERO
+    };
+
+    // snapped to whole screen pixels, so tiles don't shimmer as the camera eases
+    let scale = zoom / pixel_scale;
+    let position = ((focus + shake) / scale).round() * scale;
+    camera_transform.translation = position.extend(camera_transform.translation.z);
+    if projection.scale != scale {
+        projection.scale = scale;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{headless::HeadlessApp, tilemap::tile_to_world};
+
+    #[test]
+    fn camera_stays_on_the_map_and_lags_inside_the_dead_zone() {
+        assert_eq!(apply_dead_zone(Vec2::ZERO, Vec2::new(30., -100.), 64.), Vec2::new(0., -36.));
+
+        // 30 tiles wide and 3 high in a 640x640 view
+        let map = format!("{}\n#P{}#\n{}", "#".repeat(30), ".".repeat(27), "#".repeat(30));
+        let mut game = HeadlessApp::new(&map);
+        game.run(1);
+
+        let camera = |game: &mut HeadlessApp| {
+            let world = game.world();
+            world.query_filtered::<&Transform, With<Camera>>().single(world).translation.truncate()
+        };
+
+        // pushed in from the left edge, and centred on the map vertically
+        assert_eq!(camera(&mut game), Vec2::new(320. - 32., -64.));
+
+        // eases over until the player is back at the edge of the dead zone
+        let resting = tile_to_world(15, 1).x - TILE_SIZE;
+        game.set_player_position(tile_to_world(15, 1));
+        game.run(1);
+        let moving = camera(&mut game).x;
+        assert!(moving > 288. && moving < resting);
+
+        game.run(120);
+        assert!((camera(&mut game).x - resting).abs() < 0.5);
+    }
+}
@@ -0,0 +1,179 @@
+use std::path::PathBuf;
+
+use crate::{
+    MAP_FILE,
+    config::GameConfig,
+    generator::{self, GeneratorSettings},
+    headless::HeadlessApp,
+    replay::{Replay, Recorder, GameRng},
+    tilemap::Level,
+};
+
+// how a run was asked to start, from the command line. anything that isn't one of these
+// flags is a config setting override, see GameConfig::apply_args
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct LaunchOptions {
+    // a map file to play instead of the default one
+    pub level: Option<PathBuf>,
+    // play a generated level from this seed
+    pub seed: Option<u64>,
+    pub replay: Option<PathBuf>,
+    // write the keys pressed during the game to this file, to play back with --replay
+    pub record: Option<PathBuf>,
+    pub headless: bool,
+    // how many frames a headless run lasts
+    pub ticks: Option<usize>,
+}
+
+impl LaunchOptions {
+    // picks the launch flags out of the arguments and applies the rest to the config
+    pub fn parse(args: impl IntoIterator<Item = String>, config: &mut GameConfig) -> Result<Self, String> {
+        let mut options = LaunchOptions::default();
+        let mut settings = Vec::new();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value.clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+
+            match flag.as_str() {
+                "--level" => options.level = Some(PathBuf::from(value()?)),
+                "--seed" => {
+                    let seed = value()?;
+                    options.seed = Some(seed.parse().map_err(|_| format!("{} is not a seed", seed))?);
+                },
+                "--windowed" => {
+                    let size = value()?;
+                    let (width, height) = size.split_once('x').ok_or_else(|| format!("{} is not WIDTHxHEIGHT", size))?;
+                    config.set("window_width", width)?;
+                    config.set("window_height", height)?;
+                    config.fullscreen = false;
+                },
+                "--mute" => config.master_volume = 0.,
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--record" => options.record = Some(PathBuf::from(value()?)),
+                "--headless" => options.headless = true,
+                "--ticks" => {
+                    let ticks = value()?;
+                    options.ticks = Some(ticks.parse().map_err(|_| format!("{} is not a number of ticks", ticks))?);
+                },
+                _ => settings.push(arg),
+            }
+        }
+
+        config.apply_args(settings)?;
+
+        if options.level.is_some() && options.seed.is_some() {
+            return Err("use either --level or --seed".to_string());
+        }
+        if options.headless != options.ticks.is_some() {
+            return Err("--headless and --ticks go together".to_string());
+        }
+        if options.record.is_some() && (options.replay.is_some() || options.headless) {
+            return Err("--record can't be used with --replay or --headless".to_string());
+        }
+
+        Ok(options)
+    }
+
+    // whether to skip the start menu and go straight into a game
+    pub fn starts_in_game(&self) -> bool {
+        self.level.is_some() || self.seed.is_some() || self.replay.is_some() || self.record.is_some()
+    }
+
+    // whether frames have to be a fixed TIME_STEP apart, so a replay lines up with its recording
+    pub fn fixed_time_step(&self) -> bool {
+        self.replay.is_some() || self.record.is_some()
+    }
+
+    // the level asked for, or the default map
+    pub fn load_level(&self) -> Result<Level, String> {
+        if let Some(seed) = self.seed {
+            return Ok(generator::generate(seed, &GeneratorSettings::default()));
+        }
+
+        let path = self.level.clone().unwrap_or_else(|| PathBuf::from(MAP_FILE));
+        Level::load(&path).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn load_replay(&self) -> Result<Option<Replay>, String> {
+        self.replay
+            .as_ref()
+            .map(|path| Replay::load(path).map_err(|error| format!("{}: {}", path.display(), error)))
+            .transpose()
+    }
+
+    // what GameRng starts from: the seed a replay was recorded with, else --seed, else anything
+    pub fn rng_seed(&self, replay: Option<&Replay>) -> u64 {
+        replay
+            .and_then(|replay| replay.seed)
+            .or(self.seed)
+            .unwrap_or_else(rand::random)
+    }
+
+    pub fn start_recording(&self, seed: u64) -> Result<Option<Recorder>, String> {
+        self.record
+            .as_ref()
+            .map(|path| Recorder::create(path, seed).map_err(|error| format!("{}: {}", path.display(), error)))
+            .transpose()
+    }
+}
+
+// plays the level for the given number of frames without a window and prints how it went
+pub fn run_headless(level: Level, config: GameConfig, replay: Option<Replay>, seed: u64, ticks: usize) {
+    let mut game = HeadlessApp::with_level(level, config);
+    game.world().insert_resource(GameRng::new(seed));
+    if let Some(replay) = replay {
+        game.world().insert_resource(replay);
+    }
+
+    game.run(ticks);
+
+    let health = game.player().health;
+    println!("after {} ticks: state {:?}, score {}, health {}", ticks, game.state(), game.score(), health);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+
+    fn parse(args: &[&str]) -> Result<(LaunchOptions, GameConfig), String> {
+        let mut config = GameConfig::default();
+        let options = LaunchOptions::parse(args.iter().map(|arg| arg.to_string()), &mut config)?;
+        Ok((options, config))
+    }
+
+    #[test]
+    fn launch_flags_mix_with_config_overrides() {
+        let (options, config) = parse(&[
+            "--level", "maps/test.txt", "--windowed=800x600", "--mute",
+            "--difficulty", "hard", "--headless", "--ticks", "600",
+        ]).unwrap();
+
+        assert_eq!(options.level, Some(PathBuf::from("maps/test.txt")));
+        assert!(options.headless);
+        assert_eq!(options.ticks, Some(600));
+        assert!(options.starts_in_game());
+        assert_eq!((config.window_width, config.window_height), (800., 600.));
+        assert!(!config.fullscreen);
+        assert_eq!(config.master_volume, 0.);
+        assert_eq!(config.difficulty, Difficulty::Hard);
+
+        assert!(!parse(&[]).unwrap().0.starts_in_game());
+        assert!(parse(&["--seed", "x"]).is_err());
+        assert!(parse(&["--windowed", "800"]).is_err());
+        assert!(parse(&["--level", "a.txt", "--seed", "4"]).is_err());
+        assert!(parse(&["--headless"]).is_err());
+        assert!(parse(&["--lives", "3"]).is_err());
+        assert!(parse(&["--record", "run.txt", "--replay", "old.txt"]).is_err());
+        assert!(parse(&["--record", "run.txt"]).unwrap().0.starts_in_game());
+    }
+}
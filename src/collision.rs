@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb::collide;
+
+use crate::{GameState, spatial::SpatialIndex, movement::MovementIntegration};
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<Collision>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Game).with_system(
+                    collision_system.label(CollisionDetection).after(MovementIntegration)
+                )
+            );
+    }
+}
+
+// systems that react to Collision events run after this label
+#[derive(SystemLabel)]
+pub struct CollisionDetection;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Layer {
+    Player,
+    Enemy,
+    Wall,
+    Breakable,
+    Bomb,
+    Pickup,
+    Explosion,
+}
+
+impl Layer {
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+
+    // whether things moving around are blocked by it
+    pub fn is_solid(&self) -> bool {
+        matches!(self, Layer::Wall | Layer::Breakable)
+    }
+}
+
+// a set of layers
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct LayerMask(u8);
+
+impl LayerMask {
+    pub const NONE: LayerMask = LayerMask(0);
+
+    pub fn of(layers: &[Layer]) -> Self {
+        LayerMask(layers.iter().fold(0, |bits, layer| bits | layer.bit()))
+    }
+
+    pub fn contains(&self, layer: Layer) -> bool {
+        self.0 & layer.bit() != 0
+    }
+}
+
+// an axis aligned box around the entity's transform. `layer` is what the entity is,
+// `mask` the layers it wants Collision events for
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Collider {
+    pub size: Vec2,
+    pub offset: Vec2,
+    pub layer: Layer,
+    pub mask: LayerMask,
+    // a smaller box, no bigger than `size`, used instead of it when two colliders that both
+    // have one meet, so bodies only touch when they're really close
+    pub contact: Option<Vec2>,
+}
+
+impl Collider {
+    pub fn new(size: Vec2, layer: Layer) -> Self {
+        Self { size, offset: Vec2::ZERO, layer, mask: LayerMask::NONE, contact: None }
+    }
+
+    pub fn with_mask(self, layers: &[Layer]) -> Self {
+        Self { mask: LayerMask::of(layers), ..self }
+    }
+
+    pub fn with_contact(self, size: Vec2) -> Self {
+        Self { contact: Some(size), ..self }
+    }
+
+    pub fn centre(&self, transform: &Transform) -> Vec3 {
+        transform.translation + self.offset.extend(0.)
+    }
+
+    // whether this collider would overlap the other one with this entity at `position`
+    pub fn overlaps_at(&self, position: Vec3, other: &Collider, other_transform: &Transform) -> bool {
+        let (size, other_size) = match (self.contact, other.contact) {
+            (Some(contact), Some(other_contact)) => (contact, other_contact),
+            _ => (self.size, other.size),
+        };
+        collide(position + self.offset.extend(0.), size, other.centre(other_transform), other_size).is_some()
+    }
+}
+
+// sent to an entity each frame it overlaps something on a layer in its mask
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Collision {
+    pub entity: Entity,
+    pub other: Entity,
+    pub layer: Layer,
+}
+
+fn collision_system(
+    index: Res<SpatialIndex>,
+    collider_query: Query<(Entity, &Transform, &Collider)>,
+    mut collisions: EventWriter<Collision>,
+) {
+    for (entity, transform, collider) in collider_query.iter() {
+        if collider.mask == LayerMask::NONE {
+            continue;
+        }
+
+        let centre = collider.centre(transform);
+        for other in index.near(centre.truncate(), collider.size) {
+            let Ok((_, other_transform, other_collider)) = collider_query.get(other) else {
+                continue;
+            };
+
+            if other != entity
+                && collider.mask.contains(other_collider.layer)
+                && collider.overlaps_at(transform.translation, other_collider, other_transform)
+            {
+                collisions.send(Collision { entity, other, layer: other_collider.layer });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::Events;
+
+    use super::*;
+    use crate::headless::HeadlessApp;
+
+    #[test]
+    fn only_overlaps_on_masked_layers_send_events() {
+        let mut game = HeadlessApp::new("\
+###
+#P#
+###");
+
+        let at = |x: f32, y: f32| Transform::from_xyz(x, y, 0.);
+        let world = game.world();
+        let explosion = world.spawn((
+            at(1000., 1000.),
+            Collider::new(Vec2::splat(40.), Layer::Explosion).with_mask(&[Layer::Enemy]),
+        )).id();
+        let enemy = world.spawn((at(1030., 1000.), Collider::new(Vec2::splat(40.), Layer::Enemy))).id();
+        world.spawn((at(1000., 1030.), Collider::new(Vec2::splat(40.), Layer::Wall)));
+        world.spawn((at(1050., 1000.), Collider::new(Vec2::splat(40.), Layer::Enemy)));
+
+        // the index picks the new colliders up at the end of the first tick
+        game.run(2);
+
+        let events = game.world().resource::<Events<Collision>>();
+        let mut collisions: Vec<Collision> = events.get_reader().iter(events).copied().collect();
+        collisions.dedup();
+        assert_eq!(collisions, vec![Collision { entity: explosion, other: enemy, layer: Layer::Enemy }]);
+    }
+}
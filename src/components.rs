@@ -1,18 +1,39 @@
-use bevy::prelude::Component;
+use bevy::prelude::{Component, Vec2};
 
 // common components
 
-#[derive(Component)]
+// world units per second, set by whatever drives the entity (input, AI) and applied by
+// the movement integrator
+#[derive(Component, Default, Clone, Copy, PartialEq, Debug)]
 pub struct Velocity {
     pub x: f32, pub y: f32,
 }
 
-#[derive(Component)]
-pub struct Movable;
+impl Velocity {
+    pub fn vec(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn set(&mut self, velocity: Vec2) {
+        (self.x, self.y) = (velocity.x, velocity.y);
+    }
+}
+
+// moved by the movement integrator each frame
+#[derive(Component, Clone, Copy)]
+pub struct Movable {
+    // whether walls stop it, needs a Collider too
+    pub blocked_by_walls: bool,
+}
+
+impl Movable {
+    pub const SOLID: Movable = Movable { blocked_by_walls: true };
+    pub const PASSES_WALLS: Movable = Movable { blocked_by_walls: false };
+}
 
 // end common components
 
 // player components
 
 
-// end player components
\ No newline at end of file
+// end player components
@@ -0,0 +1,309 @@
+use bevy::prelude::*;
+
+use crate::{storage, difficulty::Difficulty};
+
+const CONFIG_FILE: &str = "config.txt";
+
+// how much each volume key changes it by
+pub const VOLUME_STEP: f32 = 0.1;
+
+// every setting, in the order they're written to the config file
+pub const KEYS: [&str; 16] = [
+    "window_width",
+    "window_height",
+    "title",
+    "fullscreen",
+    "master_volume",
+    "music_volume",
+    "sfx_volume",
+    "difficulty",
+    "bomb_time",
+    "starting_bombs",
+    "starting_health",
+    "player_speed",
+    "contact_damage",
+    "strict_movement",
+    "camera_damping",
+    "camera_dead_zone",
+];
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(volume_keys_system);
+    }
+}
+
+// player preferences and gameplay tuning, stored one `key=value` per line. keys that
+// are missing or don't parse keep their default, so an old or hand-edited file still loads
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct GameConfig {
+    pub window_width: f32,
+    pub window_height: f32,
+    pub title: String,
+    pub fullscreen: bool,
+    pub master_volume: f32,
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub difficulty: Difficulty,
+    // seconds before a bomb explodes
+    pub bomb_time: f32,
+    pub starting_bombs: usize,
+    pub starting_health: f32,
+    // tiles per second
+    pub player_speed: f32,
+    // health lost per frame touching an enemy
+    pub contact_damage: f32,
+    // walk tile to tile instead of freely with corner sliding
+    pub strict_movement: bool,
+    // how quickly the camera catches up, higher is snappier
+    pub camera_damping: f32,
+    // tiles the player can move off centre before the camera follows
+    pub camera_dead_zone: f32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            window_width: 640.,
+            window_height: 640.,
+            title: "Bomber Man!".to_string(),
+            fullscreen: false,
+            master_volume: 1.0,
+            music_volume: 0.7,
+            sfx_volume: 1.0,
+            difficulty: Difficulty::Normal,
+            bomb_time: 2.,
+            starting_bombs: 5,
+            starting_health: 100.,
+            player_speed: 6.,
+            contact_damage: 5.,
+            strict_movement: false,
+            camera_damping: 6.,
+            camera_dead_zone: 1.,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn load() -> Self {
+        match storage::read(CONFIG_FILE) {
+            Ok(Some(contents)) => GameConfig::parse(&contents),
+            Ok(None) => GameConfig::default(),
+            Err(error) => {
+                warn!("could not read the config file: {}", error);
+                GameConfig::default()
+            }
+        }
+    }
+
+    // writes the given settings back to the config file, leaving everything else in it as it
+    // was, so values passed on the command line for one run don't end up saved
+    pub fn save(&self, keys: &[&str]) {
+        let mut saved = GameConfig::load();
+        for key in keys {
+            if let Some(value) = self.get(key) {
+                saved.set(key, &value).expect("a setting's own value always parses");
+            }
+        }
+
+        if let Err(error) = storage::write(CONFIG_FILE, &saved.to_file_string()) {
+            warn!("could not save the config file: {}", error);
+        }
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let mut config = GameConfig::default();
+
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            if let Err(error) = config.set(key.trim(), value.trim()) {
+                warn!("config: {}", error);
+            }
+        }
+
+        config
+    }
+
+    // overrides settings from command line flags, `--key value` or `--key=value`,
+    // with dashes in the key standing in for underscores
+    pub fn apply_args(&mut self, args: impl IntoIterator<Item = String>) -> Result<(), String> {
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                return Err(format!("unexpected argument {}", arg));
+            };
+
+            let (key, value) = match flag.split_once('=') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => {
+                    let value = args.next().ok_or_else(|| format!("--{} needs a value", flag))?;
+                    (flag.to_string(), value)
+                },
+            };
+
+            self.set(&key.replace('-', "_"), &value)?;
+        }
+
+        Ok(())
+    }
+
+    // sets one setting from its text form
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let volume = |value: &str| {
+            value.parse::<f32>()
+                .map(|volume| volume.clamp(0., 1.))
+                .map_err(|_| format!("{} is not a volume between 0 and 1", value))
+        };
+        let positive = |value: &str| {
+            value.parse::<f32>()
+                .ok()
+                .filter(|number| *number > 0.)
+                .ok_or_else(|| format!("{} for {} is not a positive number", value, key))
+        };
+
+        let flag = |value: &str| value.parse::<bool>().map_err(|_| format!("{} is not true or false", value));
+
+        match key {
+            "window_width" => self.window_width = positive(value)?,
+            "window_height" => self.window_height = positive(value)?,
+            "title" => self.title = value.to_string(),
+            "fullscreen" => self.fullscreen = flag(value)?,
+            "master_volume" => self.master_volume = volume(value)?,
+            "music_volume" => self.music_volume = volume(value)?,
+            "sfx_volume" => self.sfx_volume = volume(value)?,
+            "difficulty" => {
+                self.difficulty = Difficulty::from_name(value)
+                    .ok_or_else(|| format!("unknown difficulty {}", value))?;
+            },
+            "bomb_time" => self.bomb_time = positive(value)?,
+            "starting_bombs" => {
+                self.starting_bombs = value.parse()
+                    .map_err(|_| format!("{} is not a number of bombs", value))?;
+            },
+            "starting_health" => self.starting_health = positive(value)?,
+            "player_speed" => self.player_speed = positive(value)?,
+            "contact_damage" => self.contact_damage = positive(value)?,
+            "strict_movement" => self.strict_movement = flag(value)?,
+            "camera_damping" => self.camera_damping = positive(value)?,
+            "camera_dead_zone" => {
+                self.camera_dead_zone = value.parse::<f32>()
+                    .ok()
+                    .filter(|tiles| *tiles >= 0.)
+                    .ok_or_else(|| format!("{} is not a number of tiles", value))?;
+            },
+            _ => return Err(format!("unknown setting {}", key)),
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let value = match key {
+            "window_width" => self.window_width.to_string(),
+            "window_height" => self.window_height.to_string(),
+            "title" => self.title.clone(),
+            "fullscreen" => self.fullscreen.to_string(),
+            "master_volume" => self.master_volume.to_string(),
+            "music_volume" => self.music_volume.to_string(),
+            "sfx_volume" => self.sfx_volume.to_string(),
+            "difficulty" => self.difficulty.name().to_string(),
+            "bomb_time" => self.bomb_time.to_string(),
+            "starting_bombs" => self.starting_bombs.to_string(),
+            "starting_health" => self.starting_health.to_string(),
+            "player_speed" => self.player_speed.to_string(),
+            "contact_damage" => self.contact_damage.to_string(),
+            "strict_movement" => self.strict_movement.to_string(),
+            "camera_damping" => self.camera_damping.to_string(),
+            "camera_dead_zone" => self.camera_dead_zone.to_string(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    pub fn to_file_string(&self) -> String {
+        KEYS.iter()
+            .filter_map(|key| self.get(key).map(|value| format!("{}={}\n", key, value)))
+            .collect()
+    }
+
+    // the configured values scaled by the chosen difficulty
+    pub fn starting_health(&self) -> f32 {
+        self.starting_health * self.difficulty.settings().starting_health
+    }
+
+    pub fn bomb_fuse(&self) -> f32 {
+        self.bomb_time * self.difficulty.settings().fuse_time
+    }
+
+    pub fn enemy_damage(&self) -> f32 {
+        self.contact_damage * self.difficulty.settings().contact_damage
+    }
+
+    pub fn music_level(&self) -> f32 {
+        self.master_volume * self.music_volume
+    }
+
+    pub fn sfx_level(&self) -> f32 {
+        self.master_volume * self.sfx_volume
+    }
+}
+
+// - and = turn the master volume down and up from anywhere in the game
+fn volume_keys_system(
+    keyboard: Res<Input<KeyCode>>,
+    mut config: ResMut<GameConfig>,
+) {
+    let step = if keyboard.just_pressed(KeyCode::Minus) {
+        -VOLUME_STEP
+    } else if keyboard.just_pressed(KeyCode::Equals) {
+        VOLUME_STEP
+    } else {
+        return;
+    };
+
+    config.master_volume = (config.master_volume + step).clamp(0., 1.);
+    config.save(&["master_volume"]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_and_keeps_defaults_for_bad_values() {
+        let config = GameConfig {
+            master_volume: 0.5,
+            difficulty: Difficulty::Hard,
+            title: "Bombs = fun".to_string(),
+            ..GameConfig::default()
+        };
+        assert_eq!(GameConfig::parse(&config.to_file_string()), config);
+
+        let loaded = GameConfig::parse("music_volume=loud\nsfx_volume=3\nnonsense\ncolour=blue\nbomb_time=-1\n");
+        assert_eq!(loaded.music_volume, GameConfig::default().music_volume);
+        assert_eq!(loaded.sfx_volume, 1.);
+        assert_eq!(loaded.bomb_time, GameConfig::default().bomb_time);
+    }
+
+    #[test]
+    fn command_line_flags_override_settings() {
+        let mut config = GameConfig::default();
+        let args = ["--bomb-time", "3.5", "--fullscreen=true", "--difficulty", "easy"];
+        config.apply_args(args.map(String::from)).unwrap();
+
+        assert_eq!(config.bomb_time, 3.5);
+        assert!(config.fullscreen);
+        assert_eq!(config.difficulty, Difficulty::Easy);
+
+        assert!(config.apply_args(["--player-speed".to_string()]).is_err());
+        assert!(config.apply_args(["--lives".to_string(), "3".to_string()]).is_err());
+        assert!(config.apply_args(["map.txt".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL.into_iter().find(|difficulty| difficulty.name() == name)
+    }
+
+    // the next difficulty in the given direction, wrapping around
+    pub fn cycle(&self, step: isize) -> Difficulty {
+        let count = Difficulty::ALL.len() as isize;
+        let index = Difficulty::ALL.iter().position(|difficulty| difficulty == self).unwrap_or(0) as isize;
+        Difficulty::ALL[(index + step).rem_euclid(count) as usize]
+    }
+}
+
+// how a difficulty scales the game. the multipliers apply on top of the values in the
+// config file, so Normal plays exactly as configured
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DifficultySettings {
+    pub enemy_speed: f32,
+    // share of a level's enemy spawns that get an enemy, above 1 adds extras on open tiles
+    pub enemy_count: f32,
+    pub contact_damage: f32,
+    pub fuse_time: f32,
+    // chance of a broken wall dropping a pickup
+    pub drop_chance: f32,
+    pub starting_health: f32,
+}
+
+const DIFFICULTY_TABLE: [(Difficulty, DifficultySettings); 3] = [
+    (Difficulty::Easy, DifficultySettings {
+        enemy_speed: 0.75,
+        enemy_count: 0.5,
+        contact_damage: 0.5,
+        fuse_time: 0.75,
+        drop_chance: 0.35,
+        starting_health: 1.5,
+    }),
+    (Difficulty::Normal, DifficultySettings {
+        enemy_speed: 1.,
+        enemy_count: 1.,
+        contact_damage: 1.,
+        fuse_time: 1.,
+        drop_chance: 0.25,
+        starting_health: 1.,
+    }),
+    (Difficulty::Hard, DifficultySettings {
+        enemy_speed: 1.3,
+        enemy_count: 1.5,
+        contact_damage: 2.,
+        fuse_time: 1.25,
+        drop_chance: 0.15,
+        starting_health: 0.75,
+    }),
+];
+
+impl Difficulty {
+    pub fn settings(&self) -> &'static DifficultySettings {
+        DIFFICULTY_TABLE
+            .iter()
+            .find(|(difficulty, _)| difficulty == self)
+            .map(|(_, settings)| settings)
+            .expect("every difficulty is in the table")
+    }
+}
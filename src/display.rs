@@ -0,0 +1,72 @@
+use bevy::{prelude::*, window::WindowResized};
+
+use crate::{GameState, WinSize, config::GameConfig};
+
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system(window_resized_system)
+            .add_system(fullscreen_toggle_system)
+            .add_system(pixel_scale_system.after(window_resized_system));
+    }
+}
+
+// keeps WinSize in step with the primary window
+fn window_resized_system(
+    mut resized: EventReader<WindowResized>,
+    mut win_size: ResMut<WinSize>,
+) {
+    for event in resized.iter().filter(|event| event.id.is_primary()) {
+        win_size.w = event.width;
+        win_size.h = event.height;
+    }
+}
+
+// F11 switches between fullscreen and a window from anywhere, and remembers the choice
+fn fullscreen_toggle_system(
+    keyboard: Res<Input<KeyCode>>,
+    mut config: ResMut<GameConfig>,
+) {
+    if keyboard.just_pressed(KeyCode::F11) {
+        config.fullscreen = !config.fullscreen;
+        config.save(&["fullscreen"]);
+    }
+}
+
+// scales the ui with the window, and the view too outside of a game, where the camera
+// system takes care of it
+fn pixel_scale_system(
+    win_size: Res<WinSize>,
+    game_state: Res<State<GameState>>,
+    mut ui_scale: ResMut<UiScale>,
+    mut camera_query: Query<&mut OrthographicProjection, With<Camera>>,
+) {
+    let scale = win_size.pixel_scale();
+
+    if win_size.is_changed() {
+        ui_scale.scale = scale as f64;
+    }
+
+    if *game_state.current() != GameState::Game {
+        for mut projection in camera_query.iter_mut() {
+            if projection.scale != 1. / scale {
+                projection.scale = 1. / scale;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WinSize;
+
+    #[test]
+    fn pixel_scale_is_a_whole_number_of_the_shorter_side() {
+        assert_eq!(WinSize { w: 800., h: 600. }.pixel_scale(), 1.);
+        assert_eq!(WinSize { w: 1920., h: 1080. }.pixel_scale(), 1.);
+        assert_eq!(WinSize { w: 2560., h: 1440. }.pixel_scale(), 2.);
+        assert_eq!(WinSize { w: 3840., h: 2160. }.pixel_scale(), 3.);
+    }
+}
@@ -0,0 +1,444 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+
+use crate::{
+    GameTextures, GameState, MainState, MAP_FILE, TILE_SIZE,
+    player::PowerUp,
+    generator::{self, GeneratorSettings},
+    highscore::GameOverInput,
+    level_clear::next_level_system,
+    tilemap::{Level, Tile, EXIT_COLOR, tile_to_world},
+    ui::{Screen, text_style, despawn_screen},
+};
+
+// the smallest grid the editor will shrink a level to
+const MIN_SIZE: usize = 3;
+
+const HELP: &str = "1-8 brush, left click paint, right click erase\n\
+arrows resize, G generate, P play test, S save, Esc menu";
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(SystemSet::on_enter(GameState::Editor).with_system(enter_editor_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Editor)
+                    .with_system(editor_keyboard_system)
+                    .with_system(paint_system)
+                    .with_system(draw_editor_system.after(editor_keyboard_system).after(paint_system))
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Editor)
+                    .with_system(despawn_screen)
+                    .with_system(despawn_editor_tiles)
+            )
+            .add_system_set(SystemSet::on_update(GameState::Game).with_system(stop_play_test_system))
+            // after the screens' own input, so a play test always ends back in the editor
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver).with_system(play_test_over_system.after(GameOverInput))
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::LevelClear).with_system(play_test_over_system.after(next_level_system))
+            );
+    }
+}
+
+// what a click puts down
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Brush {
+    Tile(Tile),
+    PlayerSpawn,
+    EnemySpawn,
+    Pickup(PowerUp),
+}
+
+impl Brush {
+    // in number key order
+    const ALL: [Brush; 8] = [
+        Brush::Tile(Tile::Wall),
+        Brush::Tile(Tile::Floor),
+        Brush::Tile(Tile::Breakable),
+        Brush::Tile(Tile::Exit),
+        Brush::Tile(Tile::HiddenExit),
+        Brush::PlayerSpawn,
+        Brush::EnemySpawn,
+        Brush::Pickup(PowerUp::ExtraBomb),
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Brush::Tile(Tile::Wall) => "wall",
+            Brush::Tile(Tile::Floor) => "floor",
+            Brush::Tile(Tile::Breakable) => "breakable wall",
+            Brush::Tile(Tile::Exit) => "exit",
+            Brush::Tile(Tile::HiddenExit) => "hidden exit",
+            Brush::PlayerSpawn => "player spawn",
+            Brush::EnemySpawn => "enemy spawn",
+            Brush::Pickup(PowerUp::ExtraBomb) => "bomb pickup",
+        }
+    }
+}
+
+// the level being edited, kept between play tests
+#[derive(Resource)]
+pub struct Editor {
+    pub level: Level,
+    pub brush: Brush,
+    pub play_testing: bool,
+    // where S saves to, the file the level came from or the default map
+    pub path: PathBuf,
+}
+
+impl Editor {
+    fn new(mut level: Level) -> Self {
+        // ragged rows are squared off so every row can be painted to the full width
+        let (width, height) = (level.width(), level.height());
+        resize(&mut level, width, height);
+
+        let path = level.path.clone().unwrap_or_else(|| PathBuf::from(MAP_FILE));
+        Self { level, brush: Brush::Tile(Tile::Wall), play_testing: false, path }
+    }
+}
+
+// puts the brush down on a tile. spawns and pickups stand on floor, and a tile holds at
+// most one of them. the player spawn can be moved but not painted over, a level always has one
+pub fn paint(level: &mut Level, tile: (usize, usize), brush: Brush) {
+    let (x, y) = tile;
+    if y >= level.height() || x >= level.tiles[y].len() {
+        return;
+    }
+    if tile == level.player_spawn && brush != Brush::PlayerSpawn {
+        return;
+    }
+
+    level.enemy_spawns.retain(|spawn| *spawn != tile);
+    level.pickups.retain(|(at, _)| *at != tile);
+    level.tiles[y][x] = match brush {
+        Brush::Tile(tile) => tile,
+        _ => Tile::Floor,
+    };
+
+    match brush {
+        Brush::Tile(_) => {},
+        Brush::PlayerSpawn => level.player_spawn = tile,
+        Brush::EnemySpawn => level.enemy_spawns.push(tile),
+        Brush::Pickup(power_up) => level.pickups.push((tile, power_up)),
+    }
+}
+
+// grows or shrinks the grid from the right and bottom edges. new tiles are walls, and
+// anything left outside the grid is dropped
+pub fn resize(level: &mut Level, width: usize, height: usize) {
+    let (width, height) = (width.max(MIN_SIZE), height.max(MIN_SIZE));
+
+    level.tiles.resize(height, Vec::new());
+    for row in level.tiles.iter_mut() {
+        row.resize(width, Tile::Wall);
+    }
+
+    let inside = |(x, y): (usize, usize)| x < width && y < height;
+    level.enemy_spawns.retain(|spawn| inside(*spawn));
+    level.pickups.retain(|(tile, _)| inside(*tile));
+
+    let (x, y) = (level.player_spawn.0.min(width - 1), level.player_spawn.1.min(height - 1));
+    level.player_spawn = (x, y);
+    level.tiles[y][x] = Tile::Floor;
+}
+
+// the tile under a world position, if it's on the grid
+fn tile_at(level: &Level, position: Vec2) -> Option<(usize, usize)> {
+    let x = (position.x / TILE_SIZE).round();
+    let y = (-position.y / TILE_SIZE).round();
+    if x < 0. || y < 0. {
+        return None;
+    }
+
+    let (x, y) = (x as usize, y as usize);
+    (x < level.width() && y < level.height()).then_some((x, y))
+}
+
+#[derive(Component)]
+struct EditorTile;
+
+#[derive(Component)]
+struct EditorText;
+
+fn enter_editor_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    level: Res<Level>,
+    editor: Option<ResMut<Editor>>,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut mouse: ResMut<Input<MouseButton>>,
+) {
+    match editor {
+        // back from a play test
+        Some(mut editor) if editor.play_testing => editor.play_testing = false,
+        // opened from the menu, on whatever level was played last
+        _ => commands.insert_resource(Editor::new(level.clone())),
+    }
+
+    // the editor runs this same frame, and the click or key that opened it shouldn't
+    // paint a tile or close it again
+    keyboard.reset_all();
+    mouse.reset_all();
+
+    commands
+        .spawn(TextBundle::from_section("", text_style(&game_textures, 18.0)).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect { left: Val::Px(10.0), top: Val::Px(10.0), ..default() },
+            ..default()
+        }))
+        .insert(EditorText)
+        .insert(Screen);
+}
+
+fn editor_keyboard_system(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    mut editor: ResMut<Editor>,
+    mut main_state: ResMut<MainState>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    const BRUSH_KEYS: [KeyCode; 8] = [
+        KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+        KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8,
+    ];
+
+    if let Some(index) = BRUSH_KEYS.iter().position(|key| keyboard.just_pressed(*key)) {
+        editor.brush = Brush::ALL[index];
+    }
+
+    let (width, height) = (editor.level.width(), editor.level.height());
+    let (new_width, new_height) = if keyboard.just_pressed(KeyCode::Right) {
+        (width + 1, height)
+    } else if keyboard.just_pressed(KeyCode::Left) {
+        (width - 1, height)
+    } else if keyboard.just_pressed(KeyCode::Down) {
+        (width, height + 1)
+    } else if keyboard.just_pressed(KeyCode::Up) {
+        (width, height - 1)
+    } else {
+        (width, height)
+    };
+    if (new_width, new_height) != (width, height) {
+        resize(&mut editor.level, new_width, new_height);
+    }
+
+    // a fresh random layout the size of the current grid
+    if keyboard.just_pressed(KeyCode::G) {
+        let settings = GeneratorSettings { width, height, ..default() };
+        editor.level = generator::generate(rand::random(), &settings);
+    }
+
+    if keyboard.just_pressed(KeyCode::S) {
+        match fs::write(&editor.path, editor.level.to_map_string()) {
+            Ok(()) => info!("level saved to {}", editor.path.display()),
+            Err(error) => warn!("could not save the level: {}", error),
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::P) {
+        editor.play_testing = true;
+        *main_state = MainState::default();
+        commands.insert_resource(editor.level.clone());
+        game_state.overwrite_set(GameState::Game).unwrap();
+    }
+
+    // Esc wins over P pressed in the same frame
+    if keyboard.just_pressed(KeyCode::Escape) {
+        game_state.overwrite_set(GameState::StartMenu).unwrap();
+    }
+}
+
+fn paint_system(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+    mut editor: ResMut<Editor>,
+) {
+    let brush = if mouse.pressed(MouseButton::Left) {
+        editor.brush
+    } else if mouse.pressed(MouseButton::Right) {
+        Brush::Tile(Tile::Floor)
+    } else {
+        return;
+    };
+
+    let Some(window) = windows.get_primary() else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    // the cursor is measured in screen pixels from the bottom left corner of the window
+    let (camera, projection) = camera_query.single();
+    let offset = cursor - Vec2::new(window.width(), window.height()) / 2.;
+    let position = offset * projection.scale + camera.translation.truncate();
+
+    let Some(tile) = tile_at(&editor.level, position) else {
+        return;
+    };
+
+    // only touch the resource when something changes, so holding the button still doesn't redraw
+    let mut level = editor.level.clone();
+    paint(&mut level, tile, brush);
+    if level != editor.level {
+        editor.level = level;
+    }
+}
+
+// redraws the whole grid whenever the level or brush changes
+fn draw_editor_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    editor: Res<Editor>,
+    tile_query: Query<Entity, With<EditorTile>>,
+    mut text_query: Query<&mut Text, With<EditorText>>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    for entity in tile_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let level = &editor.level;
+    let mut spawn_sprite = |texture: Handle<Image>, position: Vec2, z: f32, size: Vec2, color: Color| {
+        commands
+            .spawn(SpriteBundle {
+                texture,
+                sprite: Sprite { custom_size: Some(size), color, ..default() },
+                transform: Transform::from_translation(position.extend(z)),
+                ..default()
+            })
+            .insert(EditorTile);
+    };
+
+    for (y, row) in level.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let (texture, color) = match tile {
+                Tile::Wall => (game_textures.wall.clone(), Color::WHITE),
+                Tile::Floor => (game_textures.floor.clone(), Color::WHITE),
+                Tile::Breakable => (game_textures.breakable_wall.clone(), Color::WHITE),
+                Tile::Exit => (game_textures.floor.clone(), EXIT_COLOR),
+                Tile::HiddenExit => (game_textures.breakable_wall.clone(), EXIT_COLOR),
+            };
+            spawn_sprite(texture, tile_to_world(x, y), 1., Vec2::splat(TILE_SIZE), color);
+        }
+    }
+
+    for ((x, y), power_up) in level.pickups.iter() {
+        spawn_sprite(game_textures.bomb.clone(), tile_to_world(*x, *y), 5., Vec2::splat(TILE_SIZE / 2.), power_up.color());
+    }
+
+    let mut spawn_marker = |texture_atlas: Handle<TextureAtlas>, (x, y): (usize, usize)| {
+        commands
+            .spawn(SpriteSheetBundle {
+                texture_atlas,
+                sprite: TextureAtlasSprite { custom_size: Some(Vec2::new(32.0, 64.0)), ..default() },
+                transform: Transform::from_translation(tile_to_world(x, y).extend(10.)),
+                ..default()
+            })
+            .insert(EditorTile);
+    };
+
+    spawn_marker(game_textures.player.clone(), level.player_spawn);
+    for spawn in level.enemy_spawns.iter() {
+        spawn_marker(game_textures.bat.clone(), *spawn);
+    }
+
+    // keep the grid in the middle of the window
+    let last = tile_to_world(level.width().saturating_sub(1), level.height().saturating_sub(1));
+    let centre = (tile_to_world(0, 0) + last) / 2.;
+    for mut transform in camera_query.iter_mut() {
+        transform.translation = centre.extend(transform.translation.z);
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "{}\nBrush: {}   Size: {}x{}   Saves to: {}",
+            HELP, editor.brush.name(), level.width(), level.height(), editor.path.display()
+        );
+    }
+}
+
+fn despawn_editor_tiles(
+    mut commands: Commands,
+    tile_query: Query<Entity, With<EditorTile>>,
+) {
+    for entity in tile_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+// Esc during a play test goes straight back to the editor. dying or clearing the level in
+// the same frame may queue their own state, which lands back here through play_test_over_system
+fn stop_play_test_system(
+    keyboard: Res<Input<KeyCode>>,
+    editor: Option<Res<Editor>>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    if editor.is_some_and(|editor| editor.play_testing) && keyboard.just_pressed(KeyCode::Escape) {
+        game_state.overwrite_set(GameState::Editor).unwrap();
+    }
+}
+
+// and so does winning or losing one
+fn play_test_over_system(
+    editor: Option<Res<Editor>>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    if editor.is_some_and(|editor| editor.play_testing) {
+        game_state.overwrite_set(GameState::Editor).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_select::{Levels, LEVELS_DIR};
+
+    #[test]
+    fn painting_and_resizing_round_trip_through_the_map_format() {
+        let mut level = Level::parse("edited", "time=90\n#####\n#P..#\n#####");
+
+        paint(&mut level, (3, 1), Brush::EnemySpawn);
+        paint(&mut level, (2, 1), Brush::EnemySpawn);
+        paint(&mut level, (2, 1), Brush::Pickup(PowerUp::ExtraBomb));
+        paint(&mut level, (1, 1), Brush::Tile(Tile::Breakable));
+        paint(&mut level, (9, 9), Brush::Tile(Tile::Floor));
+        assert_eq!(level.to_map_string(), "time=90\n#####\n#PBE#\n#####");
+
+        resize(&mut level, 6, 4);
+        paint(&mut level, (4, 3), Brush::PlayerSpawn);
+        paint(&mut level, (1, 1), Brush::Tile(Tile::Breakable));
+        assert_eq!(level.to_map_string(), "time=90\n######\n#@BE##\n######\n####P#");
+
+        // the spawn is pulled back inside onto a floor tile
+        resize(&mut level, 3, 1);
+        assert_eq!(level.to_map_string(), "time=90\n###\n#@B\n##P");
+        assert!(level.enemy_spawns.is_empty());
+
+        let reloaded = Level::parse("edited", &level.to_map_string());
+        assert_eq!(reloaded.tiles, level.tiles);
+        assert_eq!(reloaded.pickups, level.pickups);
+        assert_eq!(reloaded.player_spawn, level.player_spawn);
+    }
+
+    #[test]
+    fn edits_save_back_to_the_file_the_level_came_from() {
+        let levels = Levels::scan(LEVELS_DIR);
+        let editor = Editor::new(levels.load(0).unwrap());
+        assert_eq!(editor.path, levels.0[0]);
+
+        let editor = Editor::new(generator::generate(1, &GeneratorSettings::default()));
+        assert_eq!(editor.path, PathBuf::from(MAP_FILE));
+    }
+}
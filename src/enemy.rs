@@ -1,9 +1,18 @@
 use crate::{
-    GameTextures, TILE_SIZE, Player, player::{self, Explosion}, PLAYER_SIZE, MainState, GameState
+    GameTextures, TILE_SIZE, Player, GameState,
+    player::{Explosion, BODY_CONTACT},
+    scoring::{self, ScoreEvent, PointsEarned},
+    components::{Movable, Velocity},
+    movement::MovementIntegration,
+    collision::{Collider, Collision, CollisionDetection, Layer},
+    tilemap::{Level, tile_to_world},
+    events::GameplayEvent,
+    config::GameConfig,
+    difficulty::Difficulty,
+    animation::{Animation, AnimationClip, AnimationMode},
 };
 
 use bevy::{prelude::*, };
-use bevy::sprite::collide_aabb::collide;
 
 pub struct EnemyPlugin;
 
@@ -13,89 +22,205 @@ impl Plugin for EnemyPlugin {
             .add_system_set(SystemSet::on_enter(GameState::Game).with_system(spawn_enemy_system))
             .add_system_set(
                 SystemSet::on_update(GameState::Game)
-                    .with_system(enemy_movement_system)
-                    .with_system(check_for_explosion_collision)
+                    .with_system(enemy_movement_system.before(MovementIntegration))
+                    .with_system(check_for_explosion_collision.label(PointsEarned).after(CollisionDetection))
             );
     }
 }
 
+// how long an enemy shrugs off further explosions after being hit, longer than an
+// explosion lasts so one bomb only ever takes one hit point
+const HURT_TIME: f32 = 1.0;
+
+// a tile wide and two tall, so with EXPLOSION_SIZE an explosion hits an enemy whose centre
+// is within 1.75 tiles across and 2.25 tiles up or down. touching the player uses BODY_CONTACT
+const ENEMY_COLLIDER: Vec2 = Vec2::new(TILE_SIZE, TILE_SIZE * 2.);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnemyKind {
+    Bat,
+    // fast and tough, sent in when the level timer runs out
+    Ogre,
+}
+
+impl EnemyKind {
+    pub fn speed(&self) -> f32 {
+        match self {
+            EnemyKind::Bat => 0.8,
+            EnemyKind::Ogre => 1.6,
+        }
+    }
+
+    pub fn health(&self) -> u32 {
+        match self {
+            EnemyKind::Bat => 1,
+            EnemyKind::Ogre => 2,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnemyKind::Bat => "bat",
+            EnemyKind::Ogre => "ogre",
+        }
+    }
+
+    // each kind's sheet is a single row of two frames
+    pub fn frame_size(&self) -> Vec2 {
+        match self {
+            EnemyKind::Bat => Vec2::new(16., 16.),
+            EnemyKind::Ogre => Vec2::new(32., 32.),
+        }
+    }
+
+    pub fn sheet(&self, texture: Handle<Image>) -> TextureAtlas {
+        TextureAtlas::from_grid(texture, self.frame_size(), 2, 1, None, None)
+    }
+
+    pub fn clip(&self) -> AnimationClip {
+        match self {
+            EnemyKind::Bat => AnimationClip::new(0, 2, 0.15, AnimationMode::Loop),
+            EnemyKind::Ogre => AnimationClip::new(0, 2, 0.3, AnimationMode::Loop),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<EnemyKind> {
+        match name {
+            "bat" => Some(EnemyKind::Bat),
+            "ogre" => Some(EnemyKind::Ogre),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Enemy {
-    speed: f32,
+    pub kind: EnemyKind,
+    pub speed: f32,
+    pub health: u32,
+    hurt_timer: Timer,
+}
+
+impl Enemy {
+    pub fn new(kind: EnemyKind) -> Self {
+        Self {
+            kind,
+            speed: kind.speed(),
+            health: kind.health(),
+            hurt_timer: Timer::from_seconds(0., TimerMode::Once),
+        }
+    }
+
+    pub fn for_difficulty(kind: EnemyKind, difficulty: Difficulty) -> Self {
+        let mut enemy = Enemy::new(kind);
+        enemy.speed *= difficulty.settings().enemy_speed;
+        enemy
+    }
 }
 
 fn spawn_enemy_system(
     mut commands: Commands,
-    game_textures: Res<GameTextures>
+    game_textures: Res<GameTextures>,
+    level: Res<Level>,
+    config: Res<GameConfig>,
 ) {
-    let enemy_positions = vec![
-        (100., 100.),
-        (200., 200.),
-        (300., 300.),
-        (100., -900.)
-    ];
-
-    for (x, y) in enemy_positions.iter() {
-        commands.spawn(SpriteBundle {
-            texture: game_textures.bat.clone(),
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(32.0, 64.0)),
-                ..Default::default()
-            },
-            transform: Transform { 
-                translation: Vec3::new(*x, *y, 101.), 
-                ..Default::default() 
-            },
-            ..Default::default()
-        })
-        .insert(Enemy {
-            speed: 0.8,
-        });
+    let count = (level.enemy_spawns.len() as f32 * config.difficulty.settings().enemy_count).ceil() as usize;
+
+    // extras beyond the map's own spawns go on the open tiles furthest from the player
+    let extras = level
+        .farthest_open_tiles(level.player_spawn)
+        .into_iter()
+        .filter(|tile| !level.enemy_spawns.contains(tile));
+
+    for (x, y) in level.enemy_spawns.iter().copied().chain(extras).take(count) {
+        let enemy = Enemy::for_difficulty(EnemyKind::Bat, config.difficulty);
+        spawn_enemy(&mut commands, &game_textures, tile_to_world(x, y), enemy);
     }
 }
 
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    position: Vec2,
+    enemy: Enemy,
+) {
+    let texture_atlas = match enemy.kind {
+        EnemyKind::Bat => game_textures.bat.clone(),
+        EnemyKind::Ogre => game_textures.ogre.clone(),
+    };
+
+    commands.spawn(SpriteSheetBundle {
+        texture_atlas,
+        sprite: TextureAtlasSprite {
+            custom_size: Some(Vec2::new(32.0, 64.0)),
+            ..Default::default()
+        },
+        transform: Transform {
+            translation: position.extend(101.),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Animation::new(enemy.kind.clip()))
+    .insert(Collider::new(ENEMY_COLLIDER, Layer::Enemy).with_contact(BODY_CONTACT))
+    .insert(Velocity::default())
+    // enemies home straight in on the player, over walls
+    .insert(Movable::PASSES_WALLS)
+    .insert(enemy);
+}
+
+// points every enemy at the player
 fn enemy_movement_system(
-    mut query: Query<(&Enemy, &mut Transform)>,
+    mut query: Query<(&Enemy, &Transform, &mut Velocity)>,
     player_query: Query<&Player>,
-    time: Res<Time>,
 ) {
     let player = player_query.single();
-    let player_position = player.position;
-    
-    for (enemy, mut transform) in query.iter_mut() {
-        let enemy_position = transform.translation;
-
-        let (mut dx, mut dy) = (player_position.x - enemy_position.x, player_position.y - enemy_position.y);
 
-        let distance = dx.hypot(dy);
-
-        (dx, dy) = (dx / distance, dy / distance);
-
-        transform.translation.x += dx * enemy.speed * TILE_SIZE * time.delta_seconds();
-        transform.translation.y += dy * enemy.speed * TILE_SIZE * time.delta_seconds();
+    for (enemy, transform, mut velocity) in query.iter_mut() {
+        let direction = (player.position - transform.translation.truncate()).normalize_or_zero();
+        velocity.set(direction * enemy.speed * TILE_SIZE);
     }
 }
 
 fn check_for_explosion_collision(
     mut commands: Commands,
-    explosion_query: Query<&Transform, With<Explosion>>,
-    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
-    mut main_state: ResMut<MainState>,
+    time: Res<Time>,
+    mut collisions: EventReader<Collision>,
+    mut explosion_query: Query<&mut Explosion>,
+    mut enemy_query: Query<(&Transform, &mut Enemy)>,
+    mut events: EventWriter<GameplayEvent>,
+    mut scores: EventWriter<ScoreEvent>,
 ) {
-    for explosion_transform in explosion_query.iter() {
-        for (enemy_entity, enemy_transform) in enemy_query.iter() {
-            let collision = collide(
-                explosion_transform.translation,
-                Vec2::splat(TILE_SIZE * 2.5),
-                enemy_transform.translation,
-                Vec2::new(PLAYER_SIZE.0,PLAYER_SIZE.1)
-            );
+    for (_, mut enemy) in enemy_query.iter_mut() {
+        enemy.hurt_timer.tick(time.delta());
+    }
+
+    let explosion_hits = collisions
+        .iter()
+        .filter(|collision| collision.layer == Layer::Enemy);
 
-            if collision.is_some() {
-                commands.entity(enemy_entity).despawn();
+    for collision in explosion_hits {
+        let (Ok(mut explosion), Ok((enemy_transform, mut enemy))) =
+            (explosion_query.get_mut(collision.entity), enemy_query.get_mut(collision.other)) else {
+            continue;
+        };
 
-                main_state.score += 100;
+        if enemy.hurt_timer.finished() && enemy.health > 0 {
+            enemy.health -= 1;
+            enemy.hurt_timer = Timer::from_seconds(HURT_TIME, TimerMode::Once);
+
+            if enemy.health == 0 {
+                commands.entity(collision.other).despawn();
+
+                let position = enemy_transform.translation.truncate();
+                explosion.kills += 1;
+                scores.send(ScoreEvent {
+                    points: scoring::enemy_points(enemy.kind) * scoring::multi_kill_multiplier(explosion.kills),
+                    owner: explosion.owner,
+                    popup_at: Some(position),
+                });
+                events.send(GameplayEvent::EnemyKilled { kind: enemy.kind, position });
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+
+use crate::{enemy::EnemyKind, player::PowerUp};
+
+pub struct GameplayEventsPlugin;
+
+impl Plugin for GameplayEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GameplayEvent>();
+    }
+}
+
+// everything noteworthy that happens during play, sent by the gameplay systems so
+// sound (and anything else that wants to react) doesn't have to know where it came from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameplayEvent {
+    BombPlaced { position: Vec2 },
+    // sent every FUSE_TICK_INTERVAL while a bomb is burning
+    FuseTick { position: Vec2 },
+    // chain is 0 for a bomb that went off on its own, see Bomb
+    Explosion { position: Vec2, chain: usize },
+    WallBroken { position: Vec2 },
+    EnemyKilled { kind: EnemyKind, position: Vec2 },
+    PickupCollected { power_up: PowerUp },
+    // sent every frame the player takes damage
+    PlayerHurt { damage: f32 },
+    PlayerDeath,
+    LevelClear,
+}
@@ -0,0 +1,157 @@
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    player::PowerUp,
+    tilemap::{Level, Tile, GameMode, DEFAULT_TIME_LIMIT},
+};
+
+// the smallest grid that still has room to move around the pillars
+const MIN_SIZE: usize = 5;
+
+// knobs for generate, odd sizes give the classic ring of corridors around every pillar
+#[derive(Clone, Debug)]
+pub struct GeneratorSettings {
+    pub width: usize,
+    pub height: usize,
+    // chance of each open tile getting a breakable wall
+    pub breakable_density: f64,
+    pub enemies: usize,
+    // tiles across plus tiles down between the player spawn and any enemy spawn
+    pub min_enemy_distance: usize,
+    // tiles around the player spawn kept free of breakable walls, so there's room for a first bomb
+    pub clear_radius: usize,
+    // pickups hidden under breakable walls
+    pub power_ups: usize,
+    pub time_limit: u32,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            width: 15,
+            height: 13,
+            breakable_density: 0.6,
+            enemies: 4,
+            min_enemy_distance: 6,
+            clear_radius: 2,
+            power_ups: 3,
+            time_limit: DEFAULT_TIME_LIMIT,
+        }
+    }
+}
+
+// a classic layout: a border and a pillar on every other tile, breakable walls scattered
+// over the rest, and the exit and power ups hidden under some of them. the same seed and
+// settings always give the same level
+pub fn generate(seed: u64, settings: &GeneratorSettings) -> Level {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (width, height) = (settings.width.max(MIN_SIZE), settings.height.max(MIN_SIZE));
+
+    let player_spawn = (1, 1);
+    let distance = |(x, y): (usize, usize)| x.abs_diff(player_spawn.0) + y.abs_diff(player_spawn.1);
+
+    let mut tiles = vec![vec![Tile::Floor; width]; height];
+    let mut breakables = Vec::new();
+    let mut floors = Vec::new();
+
+    for (y, row) in tiles.iter_mut().enumerate() {
+        for (x, tile) in row.iter_mut().enumerate() {
+            let border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            let pillar = x % 2 == 0 && y % 2 == 0;
+
+            *tile = if border || pillar {
+                Tile::Wall
+            } else if distance((x, y)) > settings.clear_radius && rng.gen_bool(settings.breakable_density.clamp(0., 1.)) {
+                breakables.push((x, y));
+                Tile::Breakable
+            } else {
+                floors.push((x, y));
+                Tile::Floor
+            };
+        }
+    }
+
+    let mut enemy_spawns: Vec<(usize, usize)> = floors
+        .iter()
+        .copied()
+        .filter(|tile| distance(*tile) >= settings.min_enemy_distance.max(settings.clear_radius + 1))
+        .collect();
+    enemy_spawns.shuffle(&mut rng);
+    enemy_spawns.truncate(settings.enemies);
+    // in reading order, the way the map format lists them
+    enemy_spawns.sort_by_key(|(x, y)| (*y, *x));
+
+    // the exit goes under the first breakable wall, the power ups under the next ones.
+    // with no breakable walls at all it sits in the open as far from the player as it can
+    breakables.shuffle(&mut rng);
+    let mut hiding_places = breakables.into_iter();
+    match hiding_places.next() {
+        Some((x, y)) => tiles[y][x] = Tile::HiddenExit,
+        None => {
+            if let Some(&(x, y)) = floors.iter().filter(|tile| !enemy_spawns.contains(tile)).max_by_key(|tile| distance(**tile)) {
+                tiles[y][x] = Tile::Exit;
+            }
+        },
+    }
+
+    let mut pickups: Vec<((usize, usize), PowerUp)> = hiding_places
+        .take(settings.power_ups)
+        .map(|tile| (tile, PowerUp::ExtraBomb))
+        .collect();
+    pickups.sort_by_key(|((x, y), _)| (*y, *x));
+
+    Level {
+        name: format!("generated-{}", seed),
+        tiles,
+        player_spawn,
+        enemy_spawns,
+        pickups,
+        time_limit: settings.time_limit,
+        mode: GameMode::Story,
+        path: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_levels_are_reproducible_and_playable() {
+        let settings = GeneratorSettings::default();
+        let level = generate(7, &settings);
+
+        assert_eq!(level, generate(7, &settings));
+        assert_ne!(level.tiles, generate(8, &settings).tiles);
+
+        // it survives a trip through the map format
+        let reparsed = Level::parse(&level.name, &level.to_map_string());
+        assert_eq!(reparsed, level);
+
+        // room to move around the spawn, and every enemy well away from it
+        assert_eq!(level.player_spawn, (1, 1));
+        for (x, y) in [(1, 1), (2, 1), (1, 2), (3, 1), (1, 3)] {
+            assert_eq!(level.tiles[y][x], Tile::Floor);
+        }
+        assert_eq!(level.enemy_spawns.len(), settings.enemies);
+        assert!(level.enemy_spawns.iter().all(|(x, y)| x + y - 2 >= settings.min_enemy_distance));
+
+        let tiles = || level.tiles.iter().flatten();
+        assert_eq!(tiles().filter(|tile| **tile == Tile::HiddenExit).count(), 1);
+        assert_eq!(level.pickups.len(), settings.power_ups);
+        assert!(level.pickups.iter().all(|((x, y), _)| level.tiles[*y][*x] == Tile::Breakable));
+
+        // with breakable walls blown away, every open tile can be walked to from the spawn
+        let mut reached = vec![level.player_spawn];
+        let mut frontier = vec![level.player_spawn];
+        while let Some((x, y)) = frontier.pop() {
+            for next in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if level.tiles[next.1][next.0] != Tile::Wall && !reached.contains(&next) {
+                    reached.push(next);
+                    frontier.push(next);
+                }
+            }
+        }
+        assert_eq!(reached.len(), tiles().filter(|tile| **tile != Tile::Wall).count());
+    }
+}
@@ -0,0 +1,586 @@
+use std::time::{Duration, Instant};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+
+use crate::{
+    GameplayPlugins, GameState, GameTextures, MainState, WinSize,
+    config::GameConfig,
+    player::Player,
+    replay::GameRng,
+    tilemap::Level,
+};
+#[cfg(test)]
+use bevy::app::AppExit;
+#[cfg(test)]
+use crate::{
+    tilemap::{Breakable, tile_to_world},
+    highscore::HighScorePlugin,
+    menu::MenuPlugin,
+    settings::SettingsPlugin,
+    level_clear::LevelClearPlugin,
+    editor::EditorPlugin,
+    level_select::{Levels, LEVELS_DIR},
+};
+
+pub const TIME_STEP: f32 = 1. / 60.;
+
+// Runs the gameplay plugins on top of MinimalPlugins, without a window, gpu or audio.
+// Time only moves when the app is ticked, by exactly one TIME_STEP per tick, and
+// keyboard input is injected by hand so a run is fully reproducible. Used by the tests
+// and by `--headless` runs from the command line.
+pub struct HeadlessApp {
+    app: App,
+    clock: Instant,
+}
+
+impl HeadlessApp {
+    #[cfg(test)]
+    pub fn new(map: &str) -> Self {
+        HeadlessApp::with_config(map, GameConfig::default())
+    }
+
+    #[cfg(test)]
+    pub fn with_config(map: &str, config: GameConfig) -> Self {
+        HeadlessApp::with_level(Level::parse("headless", map), config)
+    }
+
+    pub fn with_level(level: Level, config: GameConfig) -> Self {
+        HeadlessApp::start(HeadlessApp::gameplay_app(level, config))
+    }
+
+    // adds the menu and the screens around a run, so tests can walk through them by
+    // keyboard. anything they save goes to a scratch directory instead of the player's
+    #[cfg(test)]
+    pub fn with_screens(map: &str) -> Self {
+        std::env::set_var("BOMBERMAN_DATA_DIR", std::env::temp_dir().join(format!("bomberman-tests-{}", std::process::id())));
+
+        let mut app = HeadlessApp::gameplay_app(Level::parse("headless", map), GameConfig::default());
+        app
+            .insert_resource(Levels::scan(LEVELS_DIR))
+            .init_resource::<Gamepads>()
+            .init_resource::<Input<GamepadButton>>()
+            .add_event::<ReceivedCharacter>()
+            .add_event::<AppExit>()
+            .add_plugin(HighScorePlugin)
+            .add_plugin(MenuPlugin)
+            .add_plugin(SettingsPlugin)
+            .add_plugin(LevelClearPlugin)
+            .add_plugin(EditorPlugin)
+            .init_resource::<Input<MouseButton>>()
+            .init_resource::<Windows>()
+            .add_system_set(SystemSet::on_exit(GameState::Game).with_system(crate::despawn_game_entities));
+
+        HeadlessApp::start(app)
+    }
+
+    fn gameplay_app(level: Level, config: GameConfig) -> App {
+        let mut app = App::new();
+
+        app
+            .add_plugins(MinimalPlugins)
+            .add_state(GameState::Game)
+            .insert_resource(level)
+            .insert_resource(GameTextures::default())
+            .insert_resource(MainState::default())
+            .insert_resource(WinSize { w: config.window_width, h: config.window_height })
+            .insert_resource(config)
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(GameRng::new(0))
+            .add_plugins(GameplayPlugins);
+
+        app.world.spawn(Camera2dBundle::default());
+        app
+    }
+
+    // every system has to be added by now, the schedule can't change once it has run
+    fn start(app: App) -> Self {
+        let mut headless = HeadlessApp {
+            app,
+            clock: Instant::now(),
+        };
+
+        // the first update enters GameState::Game and spawns the level
+        headless.tick();
+        headless
+    }
+
+    #[cfg(test)]
+    pub fn set_state(&mut self, state: GameState) {
+        self.app.world.resource_mut::<State<GameState>>().overwrite_set(state).unwrap();
+        self.tick();
+    }
+
+    #[cfg(test)]
+    pub fn press(&mut self, key: KeyCode) {
+        self.app.world.resource_mut::<Input<KeyCode>>().press(key);
+    }
+
+    #[cfg(test)]
+    pub fn release(&mut self, key: KeyCode) {
+        self.app.world.resource_mut::<Input<KeyCode>>().release(key);
+    }
+
+    // presses a key for a single tick
+    #[cfg(test)]
+    pub fn tap(&mut self, key: KeyCode) {
+        self.press(key);
+        self.tick();
+        self.release(key);
+    }
+
+    pub fn tick(&mut self) {
+        self.clock += Duration::from_secs_f32(TIME_STEP);
+        self.app.insert_resource(TimeUpdateStrategy::ManualInstant(self.clock));
+        self.app.update();
+
+        // InputPlugin is not loaded, so the just pressed/released state is cleared here
+        self.app.world.resource_mut::<Input<KeyCode>>().clear();
+    }
+
+    pub fn run(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    #[cfg(test)]
+    pub fn set_player_position(&mut self, position: Vec2) {
+        let world = &mut self.app.world;
+        let (mut player, mut transform) = world.query::<(&mut Player, &mut Transform)>().single_mut(world);
+        player.position = position;
+        transform.translation = position.extend(transform.translation.z);
+    }
+
+    pub fn player(&mut self) -> &Player {
+        let world = &mut self.app.world;
+        world.query::<&Player>().single(world)
+    }
+
+    pub fn state(&self) -> GameState {
+        *self.app.world.resource::<State<GameState>>().current()
+    }
+
+    pub fn score(&self) -> usize {
+        self.app.world.resource::<MainState>().score
+    }
+
+    #[cfg(test)]
+    pub fn breakable_at(&mut self, x: usize, y: usize) -> bool {
+        let position = tile_to_world(x, y);
+        let world = &mut self.app.world;
+
+        world.query_filtered::<&Transform, With<Breakable>>()
+            .iter(world)
+            .any(|transform| transform.translation.truncate() == position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        EXTRA_LIFE_SCORE, STARTING_LIVES,
+        enemy::{Enemy, EnemyKind},
+        difficulty::Difficulty,
+        editor::Editor,
+        player::{Bomb, Invulnerable, PowerUp},
+        scoring::CHAIN_BONUS,
+        tilemap::world_to_tile,
+    };
+
+    fn bomb_ticks() -> usize {
+        (GameConfig::default().bomb_time / TIME_STEP) as usize
+    }
+
+    #[test]
+    fn bomb_destroys_adjacent_breakable_walls() {
+        let mut game = HeadlessApp::new("\
+#######
+#.....#
+#.....#
+#.@P.@#
+#.....#
+#.....#
+#######");
+
+        game.tap(KeyCode::Return);
+        game.run(bomb_ticks() + 10);
+
+        assert!(!game.breakable_at(2, 3));
+        assert!(game.breakable_at(5, 3));
+        assert_eq!(game.score(), 10);
+        assert_eq!(game.player().num_bombs, GameConfig::default().starting_bombs - 1);
+    }
+
+    #[test]
+    fn walls_block_player_movement() {
+        let mut game = HeadlessApp::new("\
+#####
+#...#
+#...#
+#.P.#
+#...#
+#...#
+#####");
+
+        game.press(KeyCode::D);
+        game.run(120);
+
+        let wall_x = tile_to_world(4, 0).x;
+        assert!(game.player().position.x > tile_to_world(2, 0).x);
+        assert!(game.player().position.x < wall_x);
+    }
+
+    #[test]
+    fn player_slides_into_a_corridor_when_slightly_off_line() {
+        let mut game = HeadlessApp::new("\
+#####
+#P..#
+##.##
+##.##
+#####");
+
+        // a bit left of the corridor leading down, the wall below would normally stop them
+        let start = tile_to_world(2, 1) - Vec2::new(12., 0.);
+        game.set_player_position(start);
+
+        game.press(KeyCode::S);
+        game.run(30);
+
+        assert_eq!(world_to_tile(game.player().position), (2, 3));
+    }
+
+    #[test]
+    fn strict_movement_stops_on_tile_centres() {
+        let config = GameConfig { strict_movement: true, ..GameConfig::default() };
+        let mut game = HeadlessApp::with_config("\
+#######
+#P....#
+#######", config);
+
+        game.tap(KeyCode::D);
+        game.run(30);
+
+        assert_eq!(game.player().position, tile_to_world(2, 1));
+    }
+
+    #[test]
+    fn enemies_chase_and_hurt_the_player() {
+        let mut game = HeadlessApp::new("\
+#######
+#.....#
+#.....#
+#.P..E#
+#.....#
+#.....#
+#######");
+
+        game.run(220);
+
+        assert!(game.player().health < GameConfig::default().starting_health);
+        assert_eq!(game.state(), GameState::Game);
+
+        let world = game.world();
+        assert_eq!(world.query::<&Enemy>().iter(world).count(), 1);
+    }
+
+    #[test]
+    fn enemies_only_hurt_on_close_contact() {
+        let mut game = HeadlessApp::new("\
+#######
+#.....#
+#.P.E.#
+#.....#
+#######");
+
+        let player = tile_to_world(2, 2);
+        let place_enemy = |game: &mut HeadlessApp, offset: Vec2| {
+            let world = game.world();
+            let (mut enemy, mut transform) = world.query::<(&mut Enemy, &mut Transform)>().single_mut(world);
+            enemy.speed = 0.;
+            transform.translation = (player + offset).extend(transform.translation.z);
+        };
+        let starting_health = GameConfig::default().starting_health;
+
+        // well inside the explosion sized enemy collider, but not touching
+        place_enemy(&mut game, Vec2::new(25., 0.));
+        game.run(5);
+        assert_eq!(game.player().health, starting_health);
+
+        place_enemy(&mut game, Vec2::new(15., 10.));
+        game.tick();
+        assert!(game.player().health < starting_health);
+    }
+
+    #[test]
+    fn running_out_of_health_on_the_last_life_ends_the_game() {
+        let mut game = HeadlessApp::new("\
+#####
+#...#
+#.P.#
+#.E.#
+#...#
+#####");
+        game.world().resource_mut::<MainState>().lives = 1;
+
+        game.run(120);
+
+        assert!(game.player().health <= 0.);
+        assert_eq!(game.state(), GameState::GameOver);
+
+        // no more hits land once the player is dying
+        let damage = GameConfig::default().enemy_damage();
+        assert!(game.player().health > -damage);
+        let damage_taken = game.world().resource::<MainState>().stats.damage_taken;
+        assert!(damage_taken < GameConfig::default().starting_health() + damage);
+    }
+
+    #[test]
+    fn dying_with_lives_left_respawns_invulnerable_with_fewer_power_ups() {
+        let mut game = HeadlessApp::new("\
+#######
+#.....#
+#P..E.#
+#.....#
+#######");
+        {
+            let world = game.world();
+            let mut player = world.query::<&mut Player>().single_mut(world);
+            player.power_ups = vec![PowerUp::ExtraBomb; 4];
+            player.num_bombs += 4;
+        }
+        let bombs = game.player().num_bombs;
+        game.set_player_position(tile_to_world(3, 2));
+
+        // dies touching the enemy, then respawns once the death animation is over
+        let mut ticks = 0;
+        while game.world().resource::<MainState>().lives == STARTING_LIVES || game.player().health <= 0. {
+            game.tick();
+            ticks += 1;
+            assert!(ticks < 600, "the player never respawned");
+        }
+
+        assert_eq!(game.state(), GameState::Game);
+        assert_eq!(game.world().resource::<MainState>().lives, STARTING_LIVES - 1);
+        assert_eq!(game.player().position, tile_to_world(1, 2));
+        assert_eq!(game.player().power_ups, [PowerUp::ExtraBomb; 2]);
+        assert_eq!(game.player().num_bombs, bombs - 2);
+
+        let world = game.world();
+        assert_eq!(world.query_filtered::<(), With<Invulnerable>>().iter(world).count(), 1);
+    }
+
+    #[test]
+    fn a_bomb_caught_in_an_explosion_goes_off_and_scores_a_chain_bonus() {
+        let mut game = HeadlessApp::new("\
+#########
+#P......#
+#########");
+
+        game.tap(KeyCode::Return);
+        game.set_player_position(tile_to_world(2, 1));
+        game.run(bomb_ticks() / 2);
+        game.tap(KeyCode::Return);
+        game.set_player_position(tile_to_world(6, 1));
+
+        // the second bomb goes off with the first, well before its own fuse is out
+        game.run(bomb_ticks() / 2 + 5);
+
+        let world = game.world();
+        assert_eq!(world.query::<&Bomb>().iter(world).count(), 0);
+        assert_eq!(game.player().score, CHAIN_BONUS);
+        assert_eq!(game.score(), CHAIN_BONUS);
+    }
+
+    #[test]
+    fn score_thresholds_award_extra_lives() {
+        let mut game = HeadlessApp::new("#####\n#P..#\n#####");
+        game.world().resource_mut::<MainState>().score = EXTRA_LIFE_SCORE * 2 + 10;
+        game.tick();
+
+        let main_state = game.world().resource::<MainState>();
+        assert_eq!(main_state.lives, STARTING_LIVES + 2);
+        assert_eq!(main_state.next_extra_life, EXTRA_LIFE_SCORE * 3);
+    }
+
+    #[test]
+    fn hidden_exit_clears_the_level_with_a_time_bonus() {
+        let mut game = HeadlessApp::new("\
+#######
+#.....#
+#.....#
+#.xP..#
+#.....#
+#.....#
+#######");
+
+        game.tap(KeyCode::Return);
+        game.run(bomb_ticks() + 10);
+        assert_eq!(game.state(), GameState::Game);
+
+        game.press(KeyCode::A);
+        game.run(15);
+
+        assert_eq!(game.state(), GameState::LevelClear);
+        assert!(game.score() > 10);
+        // the bonus went to the player who reached the exit, like any other points
+        let score = game.score();
+        assert_eq!(game.player().score, score);
+
+        // and the run's stats picked it all up
+        let stats = &game.world().resource::<MainState>().stats;
+        assert_eq!((stats.bombs_placed, stats.walls_destroyed, stats.longest_chain), (1, 1, 1));
+        // the exit counts as reached from half a tile away, give or take a frame's step
+        assert!(stats.distance_walked > 0.4 && stats.distance_walked < 2.);
+        assert_eq!(stats.level_times.len(), 1);
+        assert_eq!(stats.level_times[0].0, "headless");
+        let ticks = stats.level_times[0].1 / TIME_STEP;
+        assert!(ticks > (bomb_ticks() + 10) as f32 && ticks < (bomb_ticks() + 26) as f32);
+    }
+
+    #[test]
+    fn running_out_of_time_sends_in_ogres() {
+        let mut game = HeadlessApp::new("\
+time=1
+#######
+#.....#
+#.....#
+#.P...#
+#.....#
+#.....#
+#######");
+
+        game.run(90);
+
+        let world = game.world();
+        let ogres = world.query::<&Enemy>()
+            .iter(world)
+            .filter(|enemy| enemy.kind == EnemyKind::Ogre)
+            .count();
+        assert_eq!(ogres, 4);
+    }
+
+    #[test]
+    fn difficulty_scales_enemy_count_and_starting_health() {
+        let map = "\
+#########
+#P.....E#
+#.......#
+#E.....E#
+#.......#
+#E.....E#
+#########";
+
+        let bats = |difficulty| {
+            let config = GameConfig { difficulty, ..GameConfig::default() };
+            let mut game = HeadlessApp::with_config(map, config);
+            let health = game.player().health;
+
+            let world = game.world();
+            let count = world.query::<&Enemy>().iter(world).count();
+            (count, health)
+        };
+
+        assert_eq!(bats(Difficulty::Easy), (3, 150.));
+        assert_eq!(bats(Difficulty::Normal), (5, 100.));
+        assert_eq!(bats(Difficulty::Hard), (8, 75.));
+    }
+
+    #[test]
+    fn battle_arena_closes_in_when_time_runs_out() {
+        let mut game = HeadlessApp::new("\
+time=1
+mode=battle
+#####
+#...#
+#.P.#
+#...#
+#####");
+
+        game.run(300);
+
+        assert_eq!(game.state(), GameState::GameOver);
+    }
+
+    #[test]
+    fn enter_on_the_game_over_and_high_score_screens_stops_at_the_menu() {
+        let map = "\
+#####
+#.P.#
+#####";
+
+        let mut game = HeadlessApp::with_screens(map);
+        game.set_state(GameState::GameOver);
+        game.tap(KeyCode::Return);
+        assert_eq!(game.state(), GameState::StartMenu);
+
+        let mut game = HeadlessApp::with_screens(map);
+        game.set_state(GameState::HighScores);
+        game.tap(KeyCode::Return);
+        assert_eq!(game.state(), GameState::StartMenu);
+
+        // typing in a new high score stops at the table, not the menu or a new run
+        let mut game = HeadlessApp::with_screens(map);
+        game.world().resource_mut::<MainState>().score = 1_000_000;
+        game.set_state(GameState::GameOver);
+        game.world().send_event(ReceivedCharacter { id: bevy::window::WindowId::primary(), char: 'a' });
+        game.tap(KeyCode::Return);
+        assert_eq!(game.state(), GameState::HighScores);
+    }
+
+    #[test]
+    fn enter_on_the_level_clear_screen_starts_the_next_level_without_a_bomb() {
+        let mut game = HeadlessApp::with_screens("\
+#####
+#.P.#
+#####");
+        game.set_state(GameState::LevelClear);
+        game.tap(KeyCode::Return);
+
+        assert_eq!(game.state(), GameState::Game);
+        let world = game.world();
+        assert_eq!(world.query::<&Bomb>().iter(world).count(), 0);
+    }
+
+    #[test]
+    fn the_editor_opens_on_the_last_level_played_without_taking_the_opening_input() {
+        let mut game = HeadlessApp::with_screens("\
+#####
+#.P.#
+#####");
+        game.set_state(GameState::StartMenu);
+
+        // the click on the menu button is still held as the editor opens
+        game.world().resource_mut::<Input<MouseButton>>().press(MouseButton::Left);
+        game.set_state(GameState::Editor);
+        assert!(!game.world().resource::<Input<MouseButton>>().pressed(MouseButton::Left));
+        assert_eq!(game.world().resource::<Editor>().level.name, "headless");
+
+        game.tap(KeyCode::Escape);
+        assert_eq!(game.state(), GameState::StartMenu);
+
+        // a different level played since then is what opens next time
+        game.world().insert_resource(Level::parse("other", "#####\n#P.E#\n#####"));
+        game.set_state(GameState::Editor);
+        assert_eq!(game.world().resource::<Editor>().level.name, "other");
+    }
+
+    #[test]
+    fn leaving_settings_stops_at_the_menu() {
+        let map = "\
+#####
+#.P.#
+#####";
+
+        for key in [KeyCode::Return, KeyCode::Escape] {
+            let mut game = HeadlessApp::with_screens(map);
+            game.set_state(GameState::Settings);
+            game.tap(key);
+            assert_eq!(game.state(), GameState::StartMenu);
+        }
+    }
+}
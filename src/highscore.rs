@@ -0,0 +1,394 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameTextures, GameState, MainState, storage,
+    stats::LevelStatsFinished,
+    tilemap::Level,
+    ui::{screen_root, text_style, despawn_screen},
+};
+
+pub const MAX_ENTRIES: usize = 10;
+pub const MAX_INITIALS: usize = 3;
+
+const HIGH_SCORE_FILE: &str = "highscores.txt";
+
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(HighScores::load())
+            .init_resource::<HighScoreView>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(spawn_game_over_screen.after(LevelStatsFinished))
+            )
+            .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(initials_entry_system.label(GameOverInput)))
+            .add_system_set(SystemSet::on_exit(GameState::GameOver).with_system(despawn_screen))
+            .add_system_set(SystemSet::on_enter(GameState::HighScores).with_system(spawn_high_score_screen))
+            .add_system_set(
+                SystemSet::on_update(GameState::HighScores)
+                    .with_system(high_score_navigation_system)
+                    .with_system(update_high_score_screen)
+            )
+            .add_system_set(SystemSet::on_exit(GameState::HighScores).with_system(despawn_screen));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoreEntry {
+    pub initials: String,
+    pub score: usize,
+    pub level: String,
+}
+
+// every entry that is in the top ten overall or in the top ten of its own level.
+// stored one entry per line as `score<tab>initials<tab>level`
+#[derive(Resource, Default, Debug)]
+pub struct HighScores {
+    entries: Vec<ScoreEntry>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        match storage::read(HIGH_SCORE_FILE) {
+            Ok(Some(contents)) => HighScores::parse(&contents),
+            Ok(None) => HighScores::default(),
+            Err(error) => {
+                warn!("could not read high scores: {}", error);
+                HighScores::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::write(HIGH_SCORE_FILE, &self.to_file_string()) {
+            warn!("could not save high scores: {}", error);
+        }
+    }
+
+    // lines that don't parse are dropped, so a corrupted file loses only the damaged entries
+    pub fn parse(contents: &str) -> Self {
+        let mut high_scores = HighScores::default();
+
+        for line in contents.lines() {
+            let mut fields = line.split('\t');
+            let (Some(score), Some(initials), Some(level), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+
+            let Ok(score) = score.parse() else {
+                continue;
+            };
+
+            if !valid_initials(initials) || level.is_empty() {
+                continue;
+            }
+
+            high_scores.insert(ScoreEntry {
+                initials: initials.to_string(),
+                score,
+                level: level.to_string(),
+            });
+        }
+
+        high_scores
+    }
+
+    pub fn to_file_string(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| format!("{}\t{}\t{}\n", entry.score, entry.initials, entry.level))
+            .collect()
+    }
+
+    pub fn overall(&self) -> Vec<&ScoreEntry> {
+        self.entries.iter().take(MAX_ENTRIES).collect()
+    }
+
+    pub fn for_level(&self, level: &str) -> Vec<&ScoreEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.level == level)
+            .take(MAX_ENTRIES)
+            .collect()
+    }
+
+    // names of all levels with at least one entry, in alphabetical order
+    pub fn levels(&self) -> Vec<String> {
+        let mut levels: Vec<String> = self.entries.iter().map(|entry| entry.level.clone()).collect();
+        levels.sort();
+        levels.dedup();
+        levels
+    }
+
+    pub fn qualifies(&self, level: &str, score: usize) -> bool {
+        let beats = |table: Vec<&ScoreEntry>| {
+            table.len() < MAX_ENTRIES || table.iter().any(|entry| score > entry.score)
+        };
+
+        score > 0 && (beats(self.overall()) || beats(self.for_level(level)))
+    }
+
+    pub fn insert(&mut self, entry: ScoreEntry) {
+        // new entries go after existing entries with the same score
+        let index = self.entries.partition_point(|existing| existing.score >= entry.score);
+        self.entries.insert(index, entry);
+
+        // drop whatever fell out of both the overall and its level's table
+        let mut level_counts: Vec<(String, usize)> = Vec::new();
+        let mut rank = 0;
+        self.entries.retain(|entry| {
+            rank += 1;
+
+            let level_rank = match level_counts.iter_mut().find(|(level, _)| *level == entry.level) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                },
+                None => {
+                    level_counts.push((entry.level.clone(), 1));
+                    1
+                },
+            };
+
+            rank <= MAX_ENTRIES || level_rank <= MAX_ENTRIES
+        });
+    }
+}
+
+fn valid_initials(initials: &str) -> bool {
+    !initials.is_empty()
+        && initials.chars().count() <= MAX_INITIALS
+        && initials.chars().all(|char| char.is_ascii_alphanumeric())
+}
+
+// which table the high score screen shows: 0 is overall, then one page per level
+#[derive(Resource, Default)]
+struct HighScoreView {
+    page: usize,
+}
+
+// the game over screen has handled its keys, and may have queued the next state
+#[derive(SystemLabel)]
+pub struct GameOverInput;
+
+#[derive(Resource)]
+struct InitialsEntry {
+    initials: String,
+}
+
+#[derive(Component)]
+struct InitialsText;
+
+#[derive(Component)]
+struct HighScoreTable;
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    high_scores: Res<HighScores>,
+    main_state: Res<MainState>,
+    level: Res<Level>,
+) {
+    let qualifies = high_scores.qualifies(&level.name, main_state.score);
+    let root = screen_root(&mut commands);
+
+    commands.entity(root).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Game Over", text_style(&game_textures, 80.)));
+        parent.spawn(TextBundle::from_section(
+            format!("Score: {}", main_state.score),
+            text_style(&game_textures, 40.),
+        ));
+        parent.spawn(TextBundle::from_section(main_state.stats.report(), text_style(&game_textures, 20.)));
+
+        if qualifies {
+            parent.spawn(TextBundle::from_section(
+                "New high score! Type your initials",
+                text_style(&game_textures, 30.),
+            ));
+            parent.spawn(TextBundle::from_section("___", text_style(&game_textures, 60.)))
+                .insert(InitialsText);
+        } else {
+            parent.spawn(TextBundle::from_section(
+                "Press Enter to continue",
+                text_style(&game_textures, 30.),
+            ));
+        }
+    });
+
+    if qualifies {
+        commands.insert_resource(InitialsEntry { initials: String::new() });
+    } else {
+        commands.remove_resource::<InitialsEntry>();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn initials_entry_system(
+    mut commands: Commands,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    entry: Option<ResMut<InitialsEntry>>,
+    mut text_query: Query<&mut Text, With<InitialsText>>,
+    mut high_scores: ResMut<HighScores>,
+    mut view: ResMut<HighScoreView>,
+    main_state: Res<MainState>,
+    level: Res<Level>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    let Some(mut entry) = entry else {
+        if keyboard.just_pressed(KeyCode::Return) {
+            // the menu runs this same frame, and shouldn't see the key that opened it
+            keyboard.reset(KeyCode::Return);
+            game_state.set(GameState::StartMenu).unwrap();
+        }
+        return;
+    };
+
+    // drop whatever was typed while the game was still running
+    if entry.is_added() {
+        characters.clear();
+    } else {
+        for character in characters.iter() {
+            if character.char.is_ascii_alphanumeric() && entry.initials.len() < MAX_INITIALS {
+                entry.initials.push(character.char.to_ascii_uppercase());
+            }
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Back) {
+        entry.initials.pop();
+    }
+
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[0].value = format!("{:_<width$}", entry.initials, width = MAX_INITIALS);
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) && !entry.initials.is_empty() {
+        high_scores.insert(ScoreEntry {
+            initials: entry.initials.clone(),
+            score: main_state.score,
+            level: level.name.clone(),
+        });
+        high_scores.save();
+
+        // open the high score table on the page of the level just played
+        view.page = high_scores.levels()
+            .iter()
+            .position(|name| *name == level.name)
+            .map_or(0, |index| index + 1);
+
+        commands.remove_resource::<InitialsEntry>();
+        keyboard.reset(KeyCode::Return);
+        game_state.set(GameState::HighScores).unwrap();
+    }
+}
+
+fn spawn_high_score_screen(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+) {
+    let root = screen_root(&mut commands);
+
+    commands.entity(root).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("", text_style(&game_textures, 30.)))
+            .insert(HighScoreTable);
+        parent.spawn(TextBundle::from_section(
+            "Left/Right: change table   Enter: back",
+            text_style(&game_textures, 20.),
+        ));
+    });
+}
+
+fn high_score_navigation_system(
+    mut keyboard: ResMut<Input<KeyCode>>,
+    high_scores: Res<HighScores>,
+    mut view: ResMut<HighScoreView>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    let pages = high_scores.levels().len() + 1;
+
+    if keyboard.just_pressed(KeyCode::Right) {
+        view.page = (view.page + 1) % pages;
+    }
+    if keyboard.just_pressed(KeyCode::Left) {
+        view.page = (view.page + pages - 1) % pages;
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) || keyboard.just_pressed(KeyCode::Escape) {
+        view.page = 0;
+        keyboard.reset(KeyCode::Return);
+        game_state.set(GameState::StartMenu).unwrap();
+    }
+}
+
+fn update_high_score_screen(
+    high_scores: Res<HighScores>,
+    view: Res<HighScoreView>,
+    mut text_query: Query<&mut Text, With<HighScoreTable>>,
+) {
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+
+    let levels = high_scores.levels();
+    let page = view.page.min(levels.len());
+
+    let (title, table) = match page {
+        0 => ("Overall".to_string(), high_scores.overall()),
+        _ => (levels[page - 1].clone(), high_scores.for_level(&levels[page - 1])),
+    };
+
+    let mut value = format!("High Scores - {}\n\n", title);
+    if table.is_empty() {
+        value.push_str("No scores yet\n");
+    }
+    for (rank, entry) in table.iter().enumerate() {
+        value.push_str(&format!("{:>2}. {:<3} {:>8}  {}\n", rank + 1, entry.initials, entry.score, entry.level));
+    }
+
+    text.sections[0].value = value;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(initials: &str, score: usize, level: &str) -> ScoreEntry {
+        ScoreEntry { initials: initials.to_string(), score, level: level.to_string() }
+    }
+
+    #[test]
+    fn keeps_top_ten_overall_and_per_level() {
+        let mut high_scores = HighScores::default();
+
+        for score in 1..=15 {
+            high_scores.insert(entry("AAA", score * 100, "map"));
+        }
+        high_scores.insert(entry("BBB", 50, "other"));
+
+        let overall = high_scores.overall();
+        assert_eq!(overall.len(), MAX_ENTRIES);
+        assert_eq!(overall[0].score, 1500);
+        assert_eq!(overall[9].score, 600);
+
+        // too low for the overall table but still the best on its own level
+        assert_eq!(high_scores.for_level("other"), vec![&entry("BBB", 50, "other")]);
+        assert!(!high_scores.qualifies("map", 550));
+        assert!(high_scores.qualifies("other", 10));
+    }
+
+    #[test]
+    fn round_trips_and_skips_corrupted_lines() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(entry("JG", 300, "map"));
+        high_scores.insert(entry("ABC", 200, "map"));
+
+        let mut contents = high_scores.to_file_string();
+        contents.push_str("garbage\n\t\t\nnot_a_number\tXYZ\tmap\n100\tTOOLONG\tmap\n\u{0}\u{1}");
+
+        let loaded = HighScores::parse(&contents);
+        assert_eq!(loaded.overall(), high_scores.overall());
+    }
+}
@@ -1,60 +1,304 @@
 use bevy::prelude::*;
 
-use crate::{GameTextures, player::Player, MainState, GameState};
+use crate::{
+    GameTextures, MainState, GameState,
+    player::{Player, PowerUp},
+    config::GameConfig,
+    level_timer::LevelTimer,
+};
+
+const HUD_HEIGHT: f32 = 40.;
+const HUD_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.7);
+const HEALTH_BAR_WIDTH: f32 = 120.;
+const HEALTH_BAR_BACKGROUND: Color = Color::rgb(0.35, 0.05, 0.05);
+const HEALTH_BAR_FILL: Color = Color::rgb(0.2, 0.8, 0.2);
+const ICON_SIZE: f32 = 24.;
+const HURRY_UP_COLOR: Color = Color::rgb(1.0, 0.3, 0.3);
 
 pub struct HudPlugin;
 
 impl Plugin for HudPlugin {
     fn build(&self, app: &mut App) {
         app
+            .init_resource::<HudValues>()
             .add_system_set(SystemSet::on_enter(GameState::Game).with_system(spawn_hud))
             .add_system_set(
                 SystemSet::on_update(GameState::Game)
-                    .with_system(update_hud)
-            );
+                    .with_system(hud_values_system)
+                    .with_system(update_hud.after(hud_values_system))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Game).with_system(despawn_hud));
     }
 }
 
+// the top bar, a ui node so it stays put on screen however the camera moves
 #[derive(Component)]
 pub struct Hud;
 
+#[derive(Component)]
+struct HealthBar;
+
+#[derive(Component)]
+struct LivesText;
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct BombsText;
+
+#[derive(Component)]
+struct LevelText;
+
+// an icon and count for one kind of power up, hidden until one has been collected
+#[derive(Component)]
+struct PowerUpSlot(PowerUp);
+
+#[derive(Component)]
+struct TimeText;
+
+// everything the HUD shows. the player and MainState change nearly every frame for reasons the
+// HUD doesn't care about, so the HUD is only redrawn when these values themselves change
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+struct HudValues {
+    // 0 to 100
+    health: f32,
+    lives: usize,
+    score: usize,
+    bombs: usize,
+    // how many of each kind the player holds, in PowerUp::ALL order
+    power_ups: Vec<usize>,
+    level: usize,
+    seconds_left: u32,
+    hurry_up: bool,
+}
+
+fn power_up_counts(power_ups: &[PowerUp]) -> Vec<usize> {
+    PowerUp::ALL
+        .iter()
+        .map(|kind| power_ups.iter().filter(|power_up| *power_up == kind).count())
+        .collect()
+}
+
 fn spawn_hud(
     mut commands: Commands,
     game_textures: Res<GameTextures>,
+    main_state: Res<MainState>,
+    mut values: ResMut<HudValues>,
 ) {
-    let font = game_textures.font.clone();
+    // marks the values changed, so the new HUD is filled in on its first frame
+    *values = HudValues::default();
 
     let text_style = TextStyle {
-        font, font_size: 60., color: Color::WHITE
+        font: game_textures.font.clone(), font_size: 24., color: Color::WHITE
+    };
+
+    let item_style = Style {
+        margin: UiRect::horizontal(Val::Px(8.)),
+        ..default()
     };
 
-    let hud_text =  Text::from_sections([
-        TextSection::new("Health: 100", text_style.clone()),
-        TextSection::new("Score: 0", text_style.clone()),
-        TextSection::new("Bombs: 5", text_style.clone())
-    ]);
-
-    commands.spawn(Text2dBundle{
-        text: hud_text 
-            .with_alignment(TextAlignment::TOP_LEFT),
-        transform: Transform {
-            translation: Vec3::new(0., 0., 101.),
-            ..Default::default() 
+    commands.spawn(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.), Val::Px(HUD_HEIGHT)),
+            position_type: PositionType::Absolute,
+            position: UiRect { top: Val::Px(0.), left: Val::Px(0.), ..default() },
+            align_items: AlignItems::Center,
+            ..default()
         },
-        ..Default::default()
+        background_color: HUD_BACKGROUND.into(),
+        ..default()
     })
-    .insert(Hud);
+    .insert(Hud)
+    .with_children(|parent| {
+        // health bar, the fill node's width is the percentage of health left
+        parent.spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(HEALTH_BAR_WIDTH), Val::Px(16.)),
+                ..item_style.clone()
+            },
+            background_color: HEALTH_BAR_BACKGROUND.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    ..default()
+                },
+                background_color: HEALTH_BAR_FILL.into(),
+                ..default()
+            })
+            .insert(HealthBar);
+        });
+
+        parent.spawn(
+            TextBundle::from_section(format!("Lives: {}", main_state.lives), text_style.clone())
+                .with_style(item_style.clone())
+        )
+        .insert(LivesText);
+
+        parent.spawn(
+            TextBundle::from_section(format!("Score: {}", main_state.score), text_style.clone())
+                .with_style(item_style.clone())
+        )
+        .insert(ScoreText);
+
+        parent.spawn(ImageBundle {
+            style: Style {
+                size: Size::new(Val::Px(ICON_SIZE), Val::Px(ICON_SIZE)),
+                margin: UiRect::left(Val::Px(8.)),
+                ..default()
+            },
+            image: UiImage(game_textures.bomb.clone()),
+            ..default()
+        });
+        parent.spawn(TextBundle::from_section("x 0", text_style.clone()).with_style(item_style.clone()))
+            .insert(BombsText);
+
+        parent.spawn(NodeBundle {
+            style: Style {
+                flex_grow: 1.,
+                align_items: AlignItems::Center,
+                ..item_style.clone()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for power_up in PowerUp::ALL {
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        display: Display::None,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::right(Val::Px(8.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(PowerUpSlot(power_up))
+                .with_children(|parent| {
+                    parent.spawn(ImageBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(ICON_SIZE), Val::Px(ICON_SIZE)),
+                            margin: UiRect::right(Val::Px(2.)),
+                            ..default()
+                        },
+                        image: UiImage(game_textures.bomb.clone()),
+                        background_color: power_up.color().into(),
+                        ..default()
+                    });
+                    parent.spawn(TextBundle::from_section("", text_style.clone()));
+                });
+            }
+        });
+
+        parent.spawn(
+            TextBundle::from_section(format!("Level {}", main_state.level), text_style.clone())
+                .with_style(item_style.clone())
+        )
+        .insert(LevelText);
+
+        parent.spawn(TextBundle::from_section("", text_style).with_style(item_style))
+            .insert(TimeText);
+    });
 }
 
+fn hud_values_system(
+    config: Res<GameConfig>,
+    main_state: Res<MainState>,
+    timer: Option<Res<LevelTimer>>,
+    player_query: Query<&Player>,
+    mut values: ResMut<HudValues>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+
+    let current = HudValues {
+        health: (player.health / config.starting_health() * 100.).clamp(0., 100.),
+        lives: main_state.lives,
+        score: main_state.score,
+        bombs: player.num_bombs,
+        power_ups: power_up_counts(&player.power_ups),
+        level: main_state.level,
+        seconds_left: timer.as_ref().map_or(0, |timer| timer.remaining.ceil() as u32),
+        hurry_up: timer.is_some_and(|timer| timer.hurry_up()),
+    };
+
+    // only assigning when something differs leaves the resource unchanged the rest of the time
+    if *values != current {
+        *values = current;
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn update_hud(
-    player_query: Query<(&Transform, &Player), With<Player>>,
-    mut hud_query: Query<&mut Text, With<Hud>>,
-    main_state: Res<MainState>
+    values: Res<HudValues>,
+    mut health_bar_query: Query<&mut Style, (With<HealthBar>, Without<PowerUpSlot>)>,
+    mut slot_query: Query<(&PowerUpSlot, &mut Style, &Children), Without<HealthBar>>,
+    mut text_query: Query<(
+        &mut Text,
+        Option<&LivesText>,
+        Option<&ScoreText>,
+        Option<&BombsText>,
+        Option<&LevelText>,
+        Option<&TimeText>,
+    )>,
+) {
+    if !values.is_changed() {
+        return;
+    }
+
+    if let Ok(mut style) = health_bar_query.get_single_mut() {
+        style.size.width = Val::Percent(values.health);
+    }
+
+    for (slot, mut style, children) in slot_query.iter_mut() {
+        let index = PowerUp::ALL.iter().position(|power_up| *power_up == slot.0).unwrap();
+        let count = values.power_ups.get(index).copied().unwrap_or(0);
+
+        style.display = if count > 0 { Display::Flex } else { Display::None };
+        if let Ok((mut text, ..)) = text_query.get_mut(children[1]) {
+            text.sections[0].value = format!("x {}", count);
+        }
+    }
+
+    for (mut text, lives, score, bombs, level, time) in text_query.iter_mut() {
+        let value = if lives.is_some() {
+            format!("Lives: {}", values.lives)
+        } else if score.is_some() {
+            format!("Score: {}", values.score)
+        } else if bombs.is_some() {
+            format!("x {}", values.bombs)
+        } else if level.is_some() {
+            format!("Level {}", values.level)
+        } else if time.is_some() {
+            text.sections[0].style.color = if values.hurry_up { HURRY_UP_COLOR } else { Color::WHITE };
+            format!("{}:{:02}", values.seconds_left / 60, values.seconds_left % 60)
+        } else {
+            continue;
+        };
+
+        text.sections[0].value = value;
+    }
+}
+
+fn despawn_hud(
+    mut commands: Commands,
+    hud_query: Query<Entity, With<Hud>>,
 ) {
-    let (player_transform, player) = player_query.single();
-    let mut text = hud_query.single_mut(); 
+    for entity in hud_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
 
-    text.sections[0].value = format!("Health: {} \n", player.health);
-    text.sections[1].value = format!("Score: {} \n", main_state.score);
-    text.sections[2].value = format!("Bombs: {} \n", player.num_bombs);
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_ups_are_counted_per_kind() {
+        assert_eq!(power_up_counts(&[]), [0]);
+        assert_eq!(power_up_counts(&[PowerUp::ExtraBomb; 5]), [5]);
+    }
+}
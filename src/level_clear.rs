@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameTextures, GameState, MainState, MAP_FILE,
+    level_select::Levels,
+    level_timer::LevelTimer,
+    stats::LevelStatsFinished,
+    tilemap::Level,
+    ui::{screen_root, text_style, despawn_screen},
+};
+
+pub struct LevelClearPlugin;
+
+impl Plugin for LevelClearPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_enter(GameState::LevelClear)
+                    .with_system(spawn_level_clear_screen.after(LevelStatsFinished))
+            )
+            .add_system_set(SystemSet::on_update(GameState::LevelClear).with_system(next_level_system))
+            .add_system_set(SystemSet::on_exit(GameState::LevelClear).with_system(despawn_screen));
+    }
+}
+
+fn spawn_level_clear_screen(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    main_state: Res<MainState>,
+    timer: Res<LevelTimer>,
+) {
+    let root = screen_root(&mut commands);
+
+    commands.entity(root).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            format!("Level {} Clear!", main_state.level),
+            text_style(&game_textures, 60.),
+        ));
+        parent.spawn(TextBundle::from_section(
+            format!("Time Bonus: {}\nScore: {}", timer.bonus, main_state.score),
+            text_style(&game_textures, 30.),
+        ));
+        parent.spawn(TextBundle::from_section(main_state.stats.report(), text_style(&game_textures, 20.)));
+        parent.spawn(TextBundle::from_section("Press Enter to continue", text_style(&game_textures, 20.)));
+    });
+}
+
+pub fn next_level_system(
+    mut commands: Commands,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    levels: Res<Levels>,
+    level: Res<Level>,
+    mut main_state: ResMut<MainState>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Return) {
+        main_state.level += 1;
+
+        // story levels follow on from each other and start over after the last one,
+        // anything else goes back to the map on disk
+        let next = levels
+            .position(&level.name)
+            .and_then(|index| levels.load((index + 1) % levels.0.len()))
+            .unwrap_or_else(|| Level::load(MAP_FILE).expect("no map file"));
+        commands.insert_resource(next);
+        // the next level starts this same frame, and shouldn't drop a bomb for the key that started it
+        keyboard.reset(KeyCode::Return);
+        game_state.set(GameState::Game).unwrap();
+    }
+}
@@ -0,0 +1,308 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use bevy::prelude::*;
+
+use crate::{
+    GameTextures, GameState, MainState, storage,
+    editor::Editor,
+    tilemap::Level,
+    ui::{screen_root, text_style, despawn_screen},
+};
+
+// the story levels, played in file name order
+pub const LEVELS_DIR: &str = "assets/levels";
+
+const PROGRESS_FILE: &str = "progress.txt";
+
+const SELECTED_COLOR: Color = Color::rgb(1.0, 0.85, 0.3);
+const LOCKED_COLOR: Color = Color::rgb(0.4, 0.4, 0.4);
+
+pub struct LevelSelectPlugin;
+
+impl Plugin for LevelSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Levels::scan(LEVELS_DIR))
+            .insert_resource(LevelProgress::load())
+            .init_resource::<SelectedLevel>()
+            .init_resource::<LevelStartScore>()
+            .add_system_set(SystemSet::on_enter(GameState::Game).with_system(level_start_score_system))
+            .add_system_set(
+                SystemSet::on_enter(GameState::LevelClear).with_system(record_progress_system)
+            )
+            .add_system_set(SystemSet::on_enter(GameState::LevelSelect).with_system(spawn_level_select_screen))
+            .add_system_set(
+                SystemSet::on_update(GameState::LevelSelect)
+                    .with_system(level_select_input_system)
+                    .with_system(update_level_select_screen.after(level_select_input_system))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::LevelSelect).with_system(despawn_screen));
+    }
+}
+
+// the map files in the levels directory
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub struct Levels(pub Vec<PathBuf>);
+
+impl Levels {
+    // a missing directory is just an empty list
+    pub fn scan(dir: impl AsRef<Path>) -> Levels {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        Levels(paths)
+    }
+
+    // level names are their file stems, the same as Level::load gives them
+    pub fn name(&self, index: usize) -> String {
+        self.0[index]
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        (0..self.0.len()).find(|index| self.name(*index) == name)
+    }
+
+    pub fn load(&self, index: usize) -> Option<Level> {
+        let path = self.0.get(index)?;
+        Level::load(path)
+            .map_err(|error| warn!("could not load {}: {}", path.display(), error))
+            .ok()
+    }
+}
+
+// the levels that have been cleared and the best score on each, stored one level per
+// line as `name<tab>best score`
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub struct LevelProgress {
+    cleared: Vec<(String, usize)>,
+}
+
+impl LevelProgress {
+    pub fn load() -> Self {
+        match storage::read(PROGRESS_FILE) {
+            Ok(Some(contents)) => LevelProgress::parse(&contents),
+            Ok(None) => LevelProgress::default(),
+            Err(error) => {
+                warn!("could not read level progress: {}", error);
+                LevelProgress::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::write(PROGRESS_FILE, &self.to_file_string()) {
+            warn!("could not save level progress: {}", error);
+        }
+    }
+
+    // lines that don't parse are dropped
+    pub fn parse(contents: &str) -> Self {
+        let mut progress = LevelProgress::default();
+
+        for line in contents.lines() {
+            let Some((name, score)) = line.split_once('\t') else {
+                continue;
+            };
+            let Ok(score) = score.parse() else {
+                continue;
+            };
+
+            if !name.is_empty() {
+                progress.record(name, score);
+            }
+        }
+
+        progress
+    }
+
+    pub fn to_file_string(&self) -> String {
+        self.cleared
+            .iter()
+            .map(|(name, score)| format!("{}\t{}\n", name, score))
+            .collect()
+    }
+
+    pub fn best(&self, name: &str) -> Option<usize> {
+        self.cleared.iter().find(|(cleared, _)| cleared == name).map(|(_, score)| *score)
+    }
+
+    pub fn is_cleared(&self, name: &str) -> bool {
+        self.best(name).is_some()
+    }
+
+    // the first level is always open, every other one once the level before it is cleared
+    pub fn is_unlocked(&self, levels: &Levels, index: usize) -> bool {
+        index == 0 || self.is_cleared(&levels.name(index - 1))
+    }
+
+    pub fn record(&mut self, name: &str, score: usize) {
+        match self.cleared.iter_mut().find(|(cleared, _)| cleared == name) {
+            Some((_, best)) => *best = (*best).max(score),
+            None => self.cleared.push((name.to_string(), score)),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct SelectedLevel(usize);
+
+// the score when the current level started, so a level's score doesn't include the ones before it
+#[derive(Resource, Default)]
+struct LevelStartScore(usize);
+
+#[derive(Component)]
+struct LevelList;
+
+fn level_start_score_system(
+    main_state: Res<MainState>,
+    mut start_score: ResMut<LevelStartScore>,
+) {
+    start_score.0 = main_state.score;
+}
+
+// only levels from the levels directory count, not play tests or generated levels
+fn record_progress_system(
+    level: Res<Level>,
+    levels: Res<Levels>,
+    main_state: Res<MainState>,
+    start_score: Res<LevelStartScore>,
+    editor: Option<Res<Editor>>,
+    mut progress: ResMut<LevelProgress>,
+) {
+    // a play test keeps the name of the story level it was opened from
+    if editor.is_some_and(|editor| editor.play_testing) {
+        return;
+    }
+
+    if levels.position(&level.name).is_some() {
+        progress.record(&level.name, main_state.score.saturating_sub(start_score.0));
+        progress.save();
+    }
+}
+
+fn spawn_level_select_screen(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+) {
+    let root = screen_root(&mut commands);
+
+    commands.entity(root).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Level Select", text_style(&game_textures, 60.)));
+        parent.spawn(TextBundle::default()).insert(LevelList);
+        parent.spawn(TextBundle::from_section(
+            "Up/Down: choose   Enter: play   Esc: back",
+            text_style(&game_textures, 20.),
+        ));
+    });
+}
+
+fn level_select_input_system(
+    mut commands: Commands,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    levels: Res<Levels>,
+    progress: Res<LevelProgress>,
+    mut selected: ResMut<SelectedLevel>,
+    mut main_state: ResMut<MainState>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    let count = levels.0.len().max(1);
+
+    if keyboard.just_pressed(KeyCode::Down) {
+        selected.0 = (selected.0 + 1) % count;
+    }
+    if keyboard.just_pressed(KeyCode::Up) {
+        selected.0 = (selected.0 + count - 1) % count;
+    }
+
+    // only one change of state a frame, Esc wins over Enter
+    if keyboard.just_pressed(KeyCode::Escape) {
+        game_state.set(GameState::StartMenu).unwrap();
+        return;
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) && progress.is_unlocked(&levels, selected.0) {
+        if let Some(level) = levels.load(selected.0) {
+            *main_state = MainState { level: selected.0 + 1, ..default() };
+            commands.insert_resource(level);
+            // the level starts this same frame, and shouldn't drop a bomb for the key that started it
+            keyboard.reset(KeyCode::Return);
+            game_state.set(GameState::Game).unwrap();
+        }
+    }
+}
+
+fn update_level_select_screen(
+    game_textures: Res<GameTextures>,
+    levels: Res<Levels>,
+    progress: Res<LevelProgress>,
+    selected: Res<SelectedLevel>,
+    mut text_query: Query<&mut Text, With<LevelList>>,
+) {
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+
+    // one section per level so each can have its own colour
+    text.sections = (0..levels.0.len())
+        .map(|index| {
+            let name = levels.name(index);
+            let status = match progress.best(&name) {
+                Some(best) => format!("cleared, best {}", best),
+                None if progress.is_unlocked(&levels, index) => "not cleared".to_string(),
+                None => "locked".to_string(),
+            };
+
+            let mut style = text_style(&game_textures, 30.);
+            if index == selected.0 {
+                style.color = SELECTED_COLOR;
+            } else if !progress.is_unlocked(&levels, index) {
+                style.color = LOCKED_COLOR;
+            }
+
+            TextSection::new(format!("{}. {} - {}\n", index + 1, name, status), style)
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_unlock_in_order_and_keep_their_best_score() {
+        let levels = Levels(vec![PathBuf::from("levels/01-a.txt"), PathBuf::from("levels/02-b.txt"), PathBuf::from("levels/03-c.txt")]);
+        assert_eq!(levels.position("02-b"), Some(1));
+
+        let mut progress = LevelProgress::parse("01-a\t500\nbroken line\n\t20\n");
+        assert!(progress.is_unlocked(&levels, 0));
+        assert!(progress.is_unlocked(&levels, 1));
+        assert!(!progress.is_unlocked(&levels, 2));
+
+        progress.record("01-a", 300);
+        progress.record("02-b", 700);
+        assert_eq!(progress.best("01-a"), Some(500));
+        assert!(progress.is_unlocked(&levels, 2));
+
+        assert_eq!(LevelProgress::parse(&progress.to_file_string()), progress);
+    }
+
+    #[test]
+    fn every_story_level_is_valid() {
+        let levels = Levels::scan(LEVELS_DIR);
+        assert!(!levels.0.is_empty());
+
+        for path in levels.0.iter() {
+            let source = fs::read_to_string(path).unwrap();
+            assert_eq!(crate::validate::validate(&source), [], "{}", path.display());
+        }
+    }
+}
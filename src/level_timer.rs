@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameTextures, GameState, MainState, TILE_SIZE,
+    config::GameConfig,
+    collision::{Collider, Layer},
+    enemy::{self, Enemy, EnemyKind},
+    events::GameplayEvent,
+    scoring::{self, ScoreEvent, PointsEarned},
+    player::{Player, Bomb, Pickup},
+    tilemap::{Level, Tile, GameMode, Breakable, tile_to_world, world_to_tile, level_clear_system},
+};
+
+// below this many seconds left the hud turns red
+pub const HURRY_UP_TIME: f32 = 30.;
+
+const WAVE_SIZE: usize = 4;
+const WAVE_INTERVAL: f32 = 20.;
+const FALLING_BLOCK_INTERVAL: f32 = 0.25;
+
+pub struct LevelTimerPlugin;
+
+impl Plugin for LevelTimerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(SystemSet::on_enter(GameState::Game).with_system(start_level_timer))
+            .add_system_set(
+                SystemSet::on_update(GameState::Game)
+                    .with_system(countdown_system)
+                    .with_system(time_out_system.after(countdown_system))
+                    .with_system(time_bonus_system.label(PointsEarned).after(level_clear_system))
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct LevelTimer {
+    // seconds left on the clock
+    pub remaining: f32,
+    // score awarded for the time left over when the level was cleared
+    pub bonus: usize,
+    // counts down to the next wave of enemies or the next falling block once time is up
+    penalty: Timer,
+    // tiles still to be filled in when a battle arena closes in, outermost first
+    closing: Vec<(usize, usize)>,
+}
+
+impl LevelTimer {
+    pub fn new(level: &Level) -> Self {
+        let closing = match level.mode {
+            GameMode::Battle => closing_order(level),
+            GameMode::Story => Vec::new(),
+        };
+
+        Self {
+            remaining: level.time_limit as f32,
+            bonus: 0,
+            penalty: Timer::from_seconds(0., TimerMode::Once),
+            closing,
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        self.remaining <= 0.
+    }
+
+    pub fn hurry_up(&self) -> bool {
+        self.remaining < HURRY_UP_TIME
+    }
+}
+
+fn start_level_timer(
+    mut commands: Commands,
+    level: Res<Level>,
+) {
+    commands.insert_resource(LevelTimer::new(&level));
+}
+
+fn countdown_system(
+    time: Res<Time>,
+    mut timer: ResMut<LevelTimer>,
+) {
+    if !timer.expired() {
+        timer.remaining = (timer.remaining - time.delta_seconds()).max(0.);
+    }
+}
+
+// when the clock runs out a story level sends in waves of ogres,
+// a battle arena fills in with falling blocks from the edges inward
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn time_out_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    time: Res<Time>,
+    level: Res<Level>,
+    config: Res<GameConfig>,
+    mut timer: ResMut<LevelTimer>,
+    mut main_state: ResMut<MainState>,
+    mut player_query: Query<&mut Player>,
+    crushable_query: Query<(Entity, &Transform), Or<(With<Breakable>, With<Pickup>, With<Bomb>, With<Enemy>)>>,
+) {
+    if !timer.expired() {
+        return;
+    }
+
+    timer.penalty.tick(time.delta());
+    if !timer.penalty.finished() {
+        return;
+    }
+
+    let mut player = player_query.single_mut();
+
+    match level.mode {
+        GameMode::Story => {
+            // the open tiles furthest from the player, so a wave never lands right on top of them
+            for (x, y) in level.farthest_open_tiles(world_to_tile(player.position)).into_iter().take(WAVE_SIZE) {
+                let enemy = Enemy::for_difficulty(EnemyKind::Ogre, config.difficulty);
+                enemy::spawn_enemy(&mut commands, &game_textures, tile_to_world(x, y), enemy);
+            }
+
+            timer.penalty = Timer::from_seconds(WAVE_INTERVAL, TimerMode::Once);
+        },
+        GameMode::Battle => {
+            let Some((x, y)) = timer.closing.pop() else {
+                return;
+            };
+            let position = tile_to_world(x, y);
+
+            spawn_falling_block(&mut commands, &game_textures, position);
+
+            for (entity, transform) in crushable_query.iter() {
+                if world_to_tile(transform.translation.truncate()) == (x, y) {
+                    commands.entity(entity).despawn();
+                }
+            }
+
+            // sudden death, the arena may have closed over the spawn so there's no respawning
+            if world_to_tile(player.position) == (x, y) {
+                player.health = 0.;
+                main_state.lives = 0;
+            }
+
+            timer.penalty = Timer::from_seconds(FALLING_BLOCK_INTERVAL, TimerMode::Once);
+        },
+    }
+}
+
+fn spawn_falling_block(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    position: Vec2,
+) {
+    commands.spawn(SpriteBundle {
+        texture: game_textures.wall.clone(),
+        sprite: Sprite {
+            custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+            ..Default::default()
+        },
+        transform: Transform {
+            translation: position.extend(100.),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Collider::new(Vec2::splat(TILE_SIZE), Layer::Wall));
+}
+
+// every non-wall tile, walking the rings of the map in a spiral from the outside in.
+// reversed, so popping from the end gives the next tile to fill
+fn closing_order(level: &Level) -> Vec<(usize, usize)> {
+    let height = level.tiles.len();
+    let width = level.tiles.iter().map(Vec::len).max().unwrap_or(0);
+    let mut order = Vec::new();
+
+    let (mut left, mut top) = (0, 0);
+    let (mut right, mut bottom) = (width as isize - 1, height as isize - 1);
+
+    while left <= right && top <= bottom {
+        let mut ring = Vec::new();
+        for x in left..=right { ring.push((x, top)); }
+        for y in top + 1..=bottom { ring.push((right, y)); }
+        if top < bottom {
+            for x in (left..right).rev() { ring.push((x, bottom)); }
+        }
+        if left < right {
+            for y in (top + 1..bottom).rev() { ring.push((left, y)); }
+        }
+
+        for (x, y) in ring {
+            let (x, y) = (x as usize, y as usize);
+            let open = level.tiles.get(y).and_then(|row| row.get(x)).is_some_and(|tile| *tile != Tile::Wall);
+            if open {
+                order.push((x, y));
+            }
+        }
+
+        left += 1;
+        top += 1;
+        right -= 1;
+        bottom -= 1;
+    }
+
+    order.reverse();
+    order
+}
+
+// the time left over when a level is cleared is scored for the player who reached the exit,
+// before the game moves on to the level clear screen
+fn time_bonus_system(
+    mut events: EventReader<GameplayEvent>,
+    mut timer: ResMut<LevelTimer>,
+    player_query: Query<(Entity, &Player)>,
+    mut scores: EventWriter<ScoreEvent>,
+) {
+    if !events.iter().any(|event| *event == GameplayEvent::LevelClear) {
+        return;
+    }
+
+    timer.bonus = scoring::time_bonus(timer.remaining);
+    if timer.bonus == 0 {
+        return;
+    }
+
+    let (entity, player) = player_query.single();
+    scores.send(ScoreEvent {
+        points: timer.bonus,
+        owner: Some(entity),
+        popup_at: Some(player.position),
+    });
+}
@@ -1,42 +1,92 @@
-use bevy::{prelude::*, sprite::collide_aabb::collide};
+use std::time::Duration;
+
+use bevy::{prelude::*, app::PluginGroupBuilder, window::WindowMode, time::TimeUpdateStrategy};
 
 use player::PlayerPlugin;
 use player::Player;
-use tilemap::{TileMapPlugin, TileCollider};
-use enemy::{EnemyPlugin, };
+use tilemap::TileMapPlugin;
+use enemy::{EnemyPlugin, EnemyKind};
 use hud::HudPlugin;
+use highscore::HighScorePlugin;
+use save::SavePlugin;
+use level_timer::LevelTimerPlugin;
+use level_clear::LevelClearPlugin;
+use events::GameplayEventsPlugin;
+use sfx::SfxPlugin;
+use animation::AnimationPlugin;
+use spatial::SpatialPlugin;
+use collision::CollisionPlugin;
+use movement::MovementPlugin;
+use music::MusicPlugin;
+use config::{ConfigPlugin, GameConfig};
+use settings::SettingsPlugin;
+use editor::EditorPlugin;
+use replay::{ReplayPlugin, GameRng};
+use cli::LaunchOptions;
+use camera::CameraPlugin;
+use display::DisplayPlugin;
+use scoring::ScoringPlugin;
+use stats::{StatsPlugin, LifetimeStatsPlugin, Stats};
+use menu::MenuPlugin;
+use level_select::LevelSelectPlugin;
 
 mod player;
 mod components;
 mod tilemap;
 mod enemy;
 mod hud;
+mod highscore;
+mod save;
+mod level_timer;
+mod level_clear;
+mod events;
+mod sfx;
+mod animation;
+mod spatial;
+mod collision;
+mod movement;
+mod music;
+mod config;
+mod settings;
+mod difficulty;
+mod ui;
+mod storage;
+mod editor;
+mod generator;
+mod validate;
+mod replay;
+mod cli;
+mod camera;
+mod display;
+mod scoring;
+mod stats;
+mod menu;
+mod level_select;
+mod headless;
 
 // Asset Constants
-const PLAYER_SPRITE: &str = "player.png";
-const PLAYER_SIZE: (f32, f32) = (64.0, 128.); 
+const PLAYER_SHEET: &str = "player_sheet.png";
 
 const WALL_SPRITE: &str = "wall_tile.png";
 const FLOOR_SPRITE: &str = "floor_tile.png";
 const BOMB_SPRITE: &str = "bomb.png";
 const EXPLOSION_SHEET: &str = "explo_a_sheet.png";
 const BREAKABLE_WALL_SPRITE: &str = "breakable_wall.png";
-const BAT_SPRITE: &str = "bat.png";
+const BAT_SHEET: &str = "bat_sheet.png";
+const OGRE_SHEET: &str = "ogre_sheet.png";
 const FONT: &str = "font.ttf";
 
-const BACKGROUND_MUSIC: &str = "background-beat.ogg";
-
-const SPRITE_SCALE: f32 = 0.5;
+const MAP_FILE: &str = "assets/map.txt";
 
 const TILE_SIZE: f32 = 64.0;
-// End Asset Constants
-
-// Game Constants
-const TIME_STEP: f32 = 1. / 60.;
-const BASE_SPEED: f32 = 500.;
+// how much of the world a window shows across its shorter side at 1x, bigger windows
+// scale it up by whole numbers
+const VIEW_SIZE: f32 = 640.0;
 
-const BOMB_TIME: u64 = 2; // how long before a bomb explodes in seconds
-// End Game Constants
+const STARTING_LIVES: usize = 3;
+// an extra life every time the score passes another multiple of this
+const EXTRA_LIFE_SCORE: usize = 5000;
+// End Asset Constants
 
 // Resources
 #[derive(Resource)]
@@ -44,15 +94,27 @@ pub struct WinSize {
     pub w: f32, pub h: f32,
 }
 
-#[derive(Resource)]
+impl WinSize {
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.w, self.h)
+    }
+
+    // whole screen pixels per world pixel, so the nearest filtered tiles stay crisp
+    pub fn pixel_scale(&self) -> f32 {
+        (self.w.min(self.h) / VIEW_SIZE).floor().max(1.)
+    }
+}
+
+#[derive(Resource, Default)]
 pub struct GameTextures {
-    player: Handle<Image>,
+    player: Handle<TextureAtlas>,
     floor: Handle<Image>,
     wall: Handle<Image>,
     bomb: Handle<Image>,
     explosion: Handle<TextureAtlas>,
     breakable_wall: Handle<Image>,
-    bat: Handle<Image>,
+    bat: Handle<TextureAtlas>,
+    ogre: Handle<TextureAtlas>,
     font: Handle<Font>,
 }
 
@@ -60,6 +122,36 @@ pub struct GameTextures {
 pub struct MainState {
     // a container to hold all of the variables needed by the full game
     pub score: usize,
+    pub level: usize,
+    pub lives: usize,
+    // the score that earns the next extra life
+    pub next_extra_life: usize,
+    pub stats: Stats,
+}
+
+impl MainState {
+    // a game picked up part way through, from a save
+    pub fn resumed(score: usize, level: usize, lives: usize, stats: Stats) -> Self {
+        Self {
+            score,
+            level,
+            lives,
+            next_extra_life: (score / EXTRA_LIFE_SCORE + 1) * EXTRA_LIFE_SCORE,
+            stats,
+        }
+    }
+}
+
+impl Default for MainState {
+    fn default() -> Self {
+        Self {
+            score: 0,
+            level: 1,
+            lives: STARTING_LIVES,
+            next_extra_life: EXTRA_LIFE_SCORE,
+            stats: Stats::default(),
+        }
+    }
 }
 // End Resources
 
@@ -68,170 +160,155 @@ pub enum GameState {
     StartMenu,
     Game,
     GameOver,
+    HighScores,
+    LevelClear,
+    Settings,
+    Editor,
+    LevelSelect,
+}
+
+// the plugins that make up the game itself, without any rendering, audio or ui,
+// so they can also be run headless on top of MinimalPlugins
+pub struct GameplayPlugins;
+
+impl PluginGroup for GameplayPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(GameplayEventsPlugin)
+            .add(SpatialPlugin)
+            .add(CollisionPlugin)
+            .add(MovementPlugin)
+            .add(CameraPlugin)
+            .add(PlayerPlugin)
+            .add(ScoringPlugin)
+            .add(StatsPlugin)
+            .add(TileMapPlugin)
+            .add(EnemyPlugin)
+            .add(LevelTimerPlugin)
+            .add(AnimationPlugin)
+            .add(ReplayPlugin)
+    }
 }
 
 fn main() {
-    App::new()
-    .add_state(GameState::StartMenu)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("validate") {
+        std::process::exit(validate::run(&args[1..]));
+    }
+
+    let mut config = GameConfig::load();
+    let launch = LaunchOptions::parse(args, &mut config).and_then(|options| {
+        let (level, replay) = (options.load_level()?, options.load_replay()?);
+        let seed = options.rng_seed(replay.as_ref());
+        Ok((level, replay, options.start_recording(seed)?, seed, options))
+    });
+    let (level, replay, recorder, seed, options) = match launch {
+        Ok(launch) => launch,
+        Err(error) => {
+            eprintln!("bomberman: {}", error);
+            std::process::exit(2);
+        },
+    };
+
+    if let Some(ticks) = options.ticks {
+        cli::run_headless(level, config, replay, seed, ticks);
+        return;
+    }
+
+    let mut app = App::new();
+    app
+    .add_state(if options.starts_in_game() { GameState::Game } else { GameState::StartMenu })
+    .insert_resource(level)
+    .insert_resource(GameRng::new(seed))
     .insert_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
     .add_plugins(DefaultPlugins
         .set(WindowPlugin {
             window: WindowDescriptor {
-            title: "Bomber Man!".to_string(),
-            width: 640.,
-            height: 640.,
+            title: config.title.clone(),
+            width: config.window_width,
+            height: config.window_height,
+            mode: if config.fullscreen { WindowMode::BorderlessFullscreen } else { WindowMode::Windowed },
             ..Default::default()
         },
         ..Default::default() })
         .set(ImagePlugin::default_nearest()))
-    .add_plugin(PlayerPlugin)
-    .add_plugin(TileMapPlugin)
-    .add_plugin(EnemyPlugin)
+    .insert_resource(config)
+    .add_plugins(GameplayPlugins)
     .add_plugin(HudPlugin)
+    .add_plugin(HighScorePlugin)
+    .add_plugin(SavePlugin)
+    .add_plugin(LevelClearPlugin)
+    .add_plugin(ConfigPlugin)
+    .add_plugin(SfxPlugin)
+    .add_plugin(MusicPlugin)
+    .add_plugin(SettingsPlugin)
+    .add_plugin(EditorPlugin)
+    .add_plugin(DisplayPlugin)
+    .add_plugin(MenuPlugin)
+    .add_plugin(LevelSelectPlugin)
+    .add_plugin(LifetimeStatsPlugin)
     .add_startup_system(setup_system)
-    .add_system_set(SystemSet::on_enter(GameState::StartMenu).with_system(spawn_main_menu))
-    .add_system_set(SystemSet::on_exit(GameState::StartMenu).with_system(hide_button))
-    .add_system_set(
-        SystemSet::on_update(GameState::StartMenu)
-            .with_system(button_system)
-    )
-    .run();
+    .add_system_set(SystemSet::on_exit(GameState::Game).with_system(despawn_game_entities));
+
+    if options.fixed_time_step() {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(headless::TIME_STEP)));
+    }
+    if let Some(replay) = replay {
+        app.insert_resource(replay);
+    }
+    if let Some(recorder) = recorder {
+        app.insert_resource(recorder);
+    }
+
+    app.run();
 }
 
 fn setup_system(
     mut commands: Commands,
     assest_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut windows: ResMut<Windows>,
-    audio: Res<Audio>,
+    windows: Res<Windows>,
 ) {
     // create a camera
     commands.spawn(Camera2dBundle::default());
 
-    // Define our window size
-    let window = windows.get_primary_mut().unwrap();
-    let (win_w, win_h) = (window.width(), window.height());
+    // kept up to date by the DisplayPlugin as the window changes size
+    let window = windows.get_primary().expect("no primary window");
+    commands.insert_resource(WinSize { w: window.width(), h: window.height() });
 
     let explosion_handle = assest_server.load(EXPLOSION_SHEET); 
     let texture_atlast = TextureAtlas::from_grid(explosion_handle, Vec2::new(64., 64.), 4, 4, None, None);
     let explosion = texture_atlases.add(texture_atlast);
 
+    let player = texture_atlases.add(player::player_sheet(assest_server.load(PLAYER_SHEET)));
+    let bat = texture_atlases.add(EnemyKind::Bat.sheet(assest_server.load(BAT_SHEET)));
+    let ogre = texture_atlases.add(EnemyKind::Ogre.sheet(assest_server.load(OGRE_SHEET)));
+
     // Define our game textures
     let game_textures = GameTextures {
-        player: assest_server.load(PLAYER_SPRITE), 
+        player,
         floor: assest_server.load(FLOOR_SPRITE),
         wall: assest_server.load(WALL_SPRITE),
         bomb: assest_server.load(BOMB_SPRITE),
         explosion,
         breakable_wall: assest_server.load(BREAKABLE_WALL_SPRITE),
-        bat: assest_server.load(BAT_SPRITE),
+        bat,
+        ogre,
         font: assest_server.load(FONT),
     };
 
     commands.insert_resource(game_textures);
-    commands.insert_resource(MainState {
-        score: 0,
-    });
-
-    let music = assest_server.load(BACKGROUND_MUSIC);
-    audio.play_with_settings(
-        music,
-        PlaybackSettings {
-            repeat: true,
-            ..Default::default()
-        }
-    );
+    commands.insert_resource(MainState::default());
 }
 
-
-const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
-const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
-
-
-fn spawn_main_menu (
+// clears out the level when leaving the game, everything in the world except the camera
+// and ui has a transform, so nothing from the last run is left when a new one starts
+#[allow(clippy::type_complexity)]
+fn despawn_game_entities(
     mut commands: Commands,
-    game_textures: Res<GameTextures>,
-) {
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                ..default()
-            },
-            ..default()
-        })
-        .with_children(|parent| {
-            parent
-                .spawn(ButtonBundle {
-                    style: Style {
-                        size: Size::new(Val::Px(250.0), Val::Px(65.0)),
-                        // horizontally center child text
-                        justify_content: JustifyContent::Center,
-                        // vertically center child text
-                        align_items: AlignItems::Center,
-                        ..default()
-                    },
-                    background_color: NORMAL_BUTTON.into(),
-                    ..default()
-                })
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Press To Start",
-                        TextStyle {
-                            font: game_textures.font.clone(),
-                            font_size: 40.0,
-                            color: Color::rgb(0.9, 0.9, 0.9),
-                        },
-                    ));
-                });
-        });
-}
-
-fn button_system(
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, &Children),
-        (Changed<Interaction>, With<Button>),
-    >,
-    mut text_query: Query<&mut Text>,
-    mut game_state: ResMut<State<GameState>>
+    query: Query<Entity, (With<Transform>, Without<Camera>, Without<Node>)>,
 ) {
-    for (interaction, mut color, children) in &mut interaction_query {
-        let mut text = text_query.get_mut(children[0]).unwrap();
-        match *interaction {
-            Interaction::Clicked => {
-                text.sections[0].value = "Press to Start".to_string();
-                *color = PRESSED_BUTTON.into();
-                
-                game_state.set(GameState::Game).unwrap();
-            }
-            Interaction::Hovered => {
-                text.sections[0].value = "Press To Start".to_string();
-                *color = HOVERED_BUTTON.into();
-            }
-            Interaction::None => {
-                text.sections[0].value = "Press To Start".to_string();
-                *color = NORMAL_BUTTON.into();
-            }
-        }
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
     }
 }
-
-fn hide_button(
-    mut button_query: Query<&mut Visibility, With<Button>>,
-    children_query: Query <&Children, With<Button>>,
-    mut child_visibility_query: Query<&mut Visibility, Without<Button>>
-) {
-    let mut button_vis = button_query.single_mut(); 
-    button_vis.is_visible = false;
-
-    if let Ok(children) = children_query.get_single() {
-       for child in children.iter() {
-            if let Ok(mut child_vis) = child_visibility_query.get_mut(*child) {
-                child_vis.is_visible = false;
-            }
-       } 
-    }
-}
\ No newline at end of file
@@ -0,0 +1,323 @@
+use bevy::{prelude::*, app::AppExit};
+
+use crate::{
+    GameState, GameTextures, MainState, MAP_FILE,
+    config::GameConfig,
+    generator::{self, GeneratorSettings},
+    level_select::Levels,
+    save::{SaveGame, PendingRestore},
+    tilemap::{GameMode, Level},
+    ui::{screen_root, text_style, despawn_screen},
+};
+
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const FOCUSED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
+const PRESSED_BUTTON: Color = Color::rgb(0.35, 0.75, 0.35);
+const FOCUSED_TEXT: Color = Color::rgb(1.0, 0.85, 0.3);
+const DISABLED_TEXT: Color = Color::rgb(0.4, 0.4, 0.4);
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<MenuFocus>()
+            .add_system_set(SystemSet::on_enter(GameState::StartMenu).with_system(spawn_main_menu))
+            .add_system_set(
+                SystemSet::on_update(GameState::StartMenu)
+                    .with_system(menu_navigation_system)
+                    .with_system(difficulty_system)
+                    .with_system(update_menu_buttons.after(menu_navigation_system))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::StartMenu).with_system(despawn_screen));
+    }
+}
+
+// what each button on the start menu does
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MenuAction {
+    Story,
+    Continue,
+    Battle,
+    LevelSelect,
+    Editor,
+    HighScores,
+    Settings,
+    Quit,
+}
+
+impl MenuAction {
+    // top to bottom
+    const ALL: [MenuAction; 8] = [
+        MenuAction::Story,
+        MenuAction::Continue,
+        MenuAction::Battle,
+        MenuAction::LevelSelect,
+        MenuAction::Editor,
+        MenuAction::HighScores,
+        MenuAction::Settings,
+        MenuAction::Quit,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            MenuAction::Story => "Story",
+            MenuAction::Continue => "Continue",
+            MenuAction::Battle => "Battle",
+            MenuAction::LevelSelect => "Level Select",
+            MenuAction::Editor => "Level Editor",
+            MenuAction::HighScores => "High Scores",
+            MenuAction::Settings => "Settings",
+            MenuAction::Quit => "Quit",
+        }
+    }
+
+    // the old single letter shortcuts still work from anywhere on the menu
+    fn shortcut(&self) -> Option<KeyCode> {
+        match self {
+            MenuAction::Continue => Some(KeyCode::C),
+            MenuAction::Editor => Some(KeyCode::E),
+            MenuAction::HighScores => Some(KeyCode::H),
+            MenuAction::Settings => Some(KeyCode::S),
+            _ => None,
+        }
+    }
+
+    // its place in ALL, the order the buttons are spawned in
+    fn index(&self) -> usize {
+        MenuAction::ALL.iter().position(|action| action == self).unwrap()
+    }
+
+    // buttons that can't do anything right now are greyed out and skipped over
+    fn is_available(&self, levels: &Levels) -> bool {
+        match self {
+            MenuAction::Continue => SaveGame::exists(),
+            MenuAction::LevelSelect => !levels.0.is_empty(),
+            _ => true,
+        }
+    }
+}
+
+// which button keyboard and gamepad input acts on
+#[derive(Resource, Default)]
+struct MenuFocus(usize);
+
+#[derive(Component)]
+struct MenuButton {
+    action: MenuAction,
+    available: bool,
+}
+
+#[derive(Component)]
+struct DifficultyText;
+
+// the next available button from `focus` in the direction of `step`, wrapping around
+fn move_focus(available: &[bool], focus: usize, step: isize) -> usize {
+    let count = available.len() as isize;
+    (1..=count)
+        .map(|offset| (focus as isize + step * offset).rem_euclid(count) as usize)
+        .find(|index| available[*index])
+        .unwrap_or(focus)
+}
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    config: Res<GameConfig>,
+    levels: Res<Levels>,
+    mut focus: ResMut<MenuFocus>,
+) {
+    let available = MenuAction::ALL.map(|action| action.is_available(&levels));
+    // start on the first button that works, which is always Story
+    focus.0 = move_focus(&available, MenuAction::ALL.len() - 1, 1);
+
+    let root = screen_root(&mut commands);
+    commands.entity(root).with_children(|parent| {
+        for (action, available) in MenuAction::ALL.into_iter().zip(available) {
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(250.0), Val::Px(45.0)),
+                        margin: UiRect::all(Val::Px(4.0)),
+                        // horizontally center child text
+                        justify_content: JustifyContent::Center,
+                        // vertically center child text
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(MenuButton { action, available })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(action.label(), text_style(&game_textures, 30.)));
+                });
+        }
+
+        parent.spawn(TextBundle::from_section(difficulty_label(&config), text_style(&game_textures, 20.)))
+            .insert(DifficultyText);
+
+        parent.spawn(TextBundle::from_section(
+            "Up/Down: choose   Enter: select",
+            text_style(&game_textures, 20.),
+        ));
+    });
+}
+
+fn difficulty_label(config: &GameConfig) -> String {
+    format!("D - Difficulty: {}", config.difficulty.name())
+}
+
+// moves the focus with the arrow keys, the d-pad or the mouse, and picks the action to run
+#[allow(clippy::too_many_arguments)]
+fn menu_navigation_system(
+    mut commands: Commands,
+    mut keyboard: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    button_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    all_buttons: Query<&MenuButton>,
+    levels: Res<Levels>,
+    mut focus: ResMut<MenuFocus>,
+    mut game_state: ResMut<State<GameState>>,
+    mut main_state: ResMut<MainState>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let gamepad_pressed = |button_type| {
+        gamepads.iter().any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    };
+
+    // worked out once when the menu was spawned, checking for a save touches the disk
+    let mut available = [false; MenuAction::ALL.len()];
+    for button in all_buttons.iter() {
+        available[button.action.index()] = button.available;
+    }
+    if keyboard.just_pressed(KeyCode::Down) || gamepad_pressed(GamepadButtonType::DPadDown) {
+        focus.0 = move_focus(&available, focus.0, 1);
+    }
+    if keyboard.just_pressed(KeyCode::Up) || gamepad_pressed(GamepadButtonType::DPadUp) {
+        focus.0 = move_focus(&available, focus.0, -1);
+    }
+
+    let mut chosen = None;
+    for (interaction, button) in button_query.iter().filter(|(_, button)| button.available) {
+        match interaction {
+            Interaction::Clicked => {
+                focus.0 = button.action.index();
+                chosen = Some(button.action);
+            },
+            Interaction::Hovered => focus.0 = button.action.index(),
+            Interaction::None => {},
+        }
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) || gamepad_pressed(GamepadButtonType::South) {
+        chosen = Some(MenuAction::ALL[focus.0]);
+    }
+    if let Some(action) = MenuAction::ALL.iter().find(|action| action.shortcut().is_some_and(|key| keyboard.just_pressed(key))) {
+        chosen = Some(*action);
+    }
+
+    let Some(action) = chosen.filter(|action| available[action.index()]) else {
+        return;
+    };
+
+    // the next screen runs this same frame, and shouldn't see the key that opened it
+    keyboard.reset(KeyCode::Return);
+
+    match action {
+        MenuAction::Story => {
+            // a fresh game from the first story level, or the map on disk when there are none
+            *main_state = MainState::default();
+            let level = levels.load(0).unwrap_or_else(|| Level::load(MAP_FILE).expect("no map file"));
+            commands.insert_resource(level);
+            game_state.set(GameState::Game).unwrap();
+        },
+        MenuAction::Continue => match SaveGame::load() {
+            Ok(save_game) => {
+                *main_state = MainState::resumed(
+                    save_game.score, save_game.level_number, save_game.lives, save_game.stats.clone(),
+                );
+                commands.insert_resource(save_game.level.clone());
+                commands.insert_resource(PendingRestore(save_game));
+                game_state.set(GameState::Game).unwrap();
+            },
+            Err(error) => warn!("could not load the saved game: {}", error),
+        },
+        MenuAction::Battle => {
+            // a fresh arena every time
+            let mut level = generator::generate(rand::random(), &GeneratorSettings::default());
+            level.mode = GameMode::Battle;
+
+            *main_state = MainState::default();
+            commands.insert_resource(level);
+            game_state.set(GameState::Game).unwrap();
+        },
+        MenuAction::LevelSelect => game_state.set(GameState::LevelSelect).unwrap(),
+        MenuAction::Editor => game_state.set(GameState::Editor).unwrap(),
+        MenuAction::HighScores => game_state.set(GameState::HighScores).unwrap(),
+        MenuAction::Settings => game_state.set(GameState::Settings).unwrap(),
+        MenuAction::Quit => exit.send(AppExit),
+    }
+}
+
+fn difficulty_system(
+    keyboard: Res<Input<KeyCode>>,
+    mut config: ResMut<GameConfig>,
+    mut difficulty_text_query: Query<&mut Text, With<DifficultyText>>,
+) {
+    if keyboard.just_pressed(KeyCode::D) {
+        config.difficulty = config.difficulty.cycle(1);
+        config.save(&["difficulty"]);
+
+        if let Ok(mut text) = difficulty_text_query.get_single_mut() {
+            text.sections[0].value = difficulty_label(&config);
+        }
+    }
+}
+
+fn update_menu_buttons(
+    focus: Res<MenuFocus>,
+    mut button_query: Query<(&Interaction, &MenuButton, &mut BackgroundColor, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, button, mut color, children) in button_query.iter_mut() {
+        let focused = MenuAction::ALL[focus.0] == button.action;
+
+        *color = match (interaction, focused) {
+            _ if !button.available => NORMAL_BUTTON,
+            (Interaction::Clicked, _) => PRESSED_BUTTON,
+            (_, true) => FOCUSED_BUTTON,
+            _ => NORMAL_BUTTON,
+        }.into();
+
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].style.color = if !button.available {
+                DISABLED_TEXT
+            } else if focused {
+                FOCUSED_TEXT
+            } else {
+                Color::rgb(0.9, 0.9, 0.9)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focus_skips_unavailable_buttons_and_wraps() {
+        let available = [true, false, true, false, true];
+
+        assert_eq!(move_focus(&available, 0, 1), 2);
+        assert_eq!(move_focus(&available, 2, 1), 4);
+        assert_eq!(move_focus(&available, 4, 1), 0);
+        assert_eq!(move_focus(&available, 0, -1), 4);
+        assert_eq!(move_focus(&available, 2, -1), 0);
+
+        // nothing else to go to
+        assert_eq!(move_focus(&[false, true, false], 1, 1), 1);
+    }
+}
@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+
+use crate::{
+    GameState, TILE_SIZE,
+    components::{Movable, Velocity},
+    collision::Collider,
+    spatial::SpatialIndex,
+};
+
+pub struct MovementPlugin;
+
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::on_update(GameState::Game).with_system(integrate_movement_system.label(MovementIntegration))
+        );
+    }
+}
+
+// systems that set a Velocity run before this label, ones that react to where things
+// ended up run after it
+#[derive(SystemLabel)]
+pub struct MovementIntegration;
+
+// the tile centre a grid locked mover is walking to. entities with one only turn on tile
+// centres, and finish the step they're on when their velocity drops to zero
+#[derive(Component, Default)]
+pub struct TileStep {
+    target: Option<Vec2>,
+    // how fast the last step was taken, to finish it at
+    speed: f32,
+}
+
+impl TileStep {
+    pub fn clear(&mut self) {
+        self.target = None;
+    }
+}
+
+// moves every Movable by its Velocity. solid movers with a Collider are stopped by walls,
+// sliding around corners they only just clip. afterwards Velocity holds how fast the
+// entity actually moved, so a blocked axis reads as zero
+#[allow(clippy::type_complexity)]
+fn integrate_movement_system(
+    mut query: Query<(&mut Transform, &mut Velocity, &Movable, Option<&Collider>, Option<&mut TileStep>)>,
+    walls: Walls,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+    if delta == 0. {
+        return;
+    }
+
+    for (mut transform, mut velocity, movable, collider, tile_step) in query.iter_mut() {
+        let intended = velocity.vec();
+        let start = transform.translation;
+
+        let solid = collider.filter(|_| movable.blocked_by_walls);
+
+        let position = match (solid, tile_step) {
+            (Some(collider), Some(mut tile_step)) => {
+                if intended != Vec2::ZERO {
+                    tile_step.speed = intended.abs().max_element();
+                }
+                let direction = Vec2::new(sign(intended.x), sign(intended.y));
+                step_between_tiles(start, direction, tile_step.speed * delta, &mut tile_step, &walls, collider)
+            },
+            (Some(collider), None) => {
+                let moved = move_along(start, Vec2::new(intended.x * delta, 0.), &walls, collider);
+                move_along(moved, Vec2::new(0., intended.y * delta), &walls, collider)
+            },
+            (None, _) => start + (intended * delta).extend(0.),
+        };
+
+        // only touched when it moved, so resting entities aren't marked as changed
+        if position != start {
+            transform.translation = position;
+        }
+
+        let moved = (position - start).truncate() / delta;
+        if moved != intended {
+            velocity.set(moved);
+        }
+    }
+}
+
+// -1, 0 or 1, unlike f32::signum which gives 1 for zero
+fn sign(value: f32) -> f32 {
+    if value == 0. { 0. } else { value.signum() }
+}
+
+// moves along one axis. when a wall is in the way but the mover is only slightly off the
+// centre line of a corridor leading past it, they're slid onto that line instead, so
+// turning into a corridor doesn't need pixel perfect lining up
+fn move_along(
+    position: Vec3,
+    step: Vec2,
+    walls: &Walls,
+    collider: &Collider,
+) -> Vec3 {
+    if step == Vec2::ZERO {
+        return position;
+    }
+
+    let target = position + step.extend(0.0);
+    if walls.is_free(target, collider) {
+        return target;
+    }
+
+    let across = if step.x != 0.0 { Vec2::Y } else { Vec2::X };
+    let offset = position.truncate().dot(across);
+    let lane = (offset / TILE_SIZE).round() * TILE_SIZE;
+    if lane == offset {
+        return position;
+    }
+
+    let on_lane = position + (across * (lane - offset)).extend(0.0);
+    if !walls.is_free(on_lane + step.extend(0.0), collider) {
+        return position;
+    }
+
+    let slide = (lane - offset).clamp(-step.length(), step.length());
+    let target = position + (across * slide).extend(0.0);
+    if walls.is_free(target, collider) { target } else { position }
+}
+
+// strict mode: always walks from one tile centre to the next and can only change
+// direction on a tile centre
+fn step_between_tiles(
+    mut position: Vec3,
+    input: Vec2,
+    mut distance: f32,
+    tile_step: &mut TileStep,
+    walls: &Walls,
+    collider: &Collider,
+) -> Vec3 {
+    loop {
+        let target = match tile_step.target {
+            Some(target) => target,
+            None => {
+                let here = (position.truncate() / TILE_SIZE).round() * TILE_SIZE;
+                let direction = if input.x != 0.0 {
+                    Vec2::new(input.x, 0.0)
+                } else {
+                    Vec2::new(0.0, input.y)
+                };
+
+                // head for the next tile if it's open, otherwise settle on the current one
+                let next = here + direction * TILE_SIZE;
+                let target = if direction != Vec2::ZERO && walls.is_free(next.extend(position.z), collider) {
+                    next
+                } else {
+                    here
+                };
+
+                if target == position.truncate() {
+                    return position;
+                }
+                tile_step.target = Some(target);
+                target
+            },
+        };
+
+        let to_target = target - position.truncate();
+        let length = to_target.length();
+        if length > distance {
+            return position + (to_target / length * distance).extend(0.0);
+        }
+
+        // arrived, keep going with whatever distance is left this frame
+        position = target.extend(position.z);
+        distance -= length;
+        tile_step.target = None;
+
+        if distance <= 0.0 {
+            return position;
+        }
+    }
+}
+
+// the walls movers can't pass through, looked up through the spatial index
+#[derive(SystemParam)]
+pub struct Walls<'w, 's> {
+    index: Res<'w, SpatialIndex>,
+    query: Query<'w, 's, (&'static Transform, &'static Collider), Without<Movable>>,
+}
+
+impl<'w, 's> Walls<'w, 's> {
+    // whether the collider fits at this position without overlapping anything solid
+    fn is_free(&self, position: Vec3, collider: &Collider) -> bool {
+        !self.index
+            .near(position.truncate(), collider.size)
+            .filter_map(|entity| self.query.get(entity).ok())
+            .any(|(other_transform, other)| {
+                other.layer.is_solid() && collider.overlaps_at(position, other, other_transform)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{collision::Layer, headless::HeadlessApp, tilemap::tile_to_world};
+
+    #[test]
+    fn solid_movers_stop_at_walls_and_others_pass_through() {
+        let mut game = HeadlessApp::new("\
+######
+#P...#
+######");
+
+        let mut spawn_mover = |movable: Movable| {
+            game.world().spawn((
+                Transform::from_translation(tile_to_world(2, 1).extend(0.)),
+                Velocity { x: 5. * TILE_SIZE, y: 0. },
+                movable,
+                Collider::new(Vec2::splat(TILE_SIZE / 2.), Layer::Bomb),
+            )).id()
+        };
+        let (sliding, ghost) = (spawn_mover(Movable::SOLID), spawn_mover(Movable::PASSES_WALLS));
+
+        game.run(60);
+
+        let world = game.world();
+        let wall_x = tile_to_world(5, 1).x;
+        let x = world.get::<Transform>(sliding).unwrap().translation.x;
+        assert!(x > tile_to_world(4, 1).x && x <= wall_x - TILE_SIZE * 0.75);
+        assert_eq!(*world.get::<Velocity>(sliding).unwrap(), Velocity::default());
+        assert!(world.get::<Transform>(ghost).unwrap().translation.x > wall_x);
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::{prelude::*, audio::AudioSink};
+
+use crate::{GameState, MainState, config::GameConfig, level_timer::LevelTimer};
+
+const MENU_MUSIC: &str = "music/menu.wav";
+const GAME_OVER_MUSIC: &str = "music/game_over.wav";
+// levels cycle through these themes in order
+const LEVEL_MUSIC: [&str; 2] = ["background-beat.ogg", "music/level_2.wav"];
+
+// the hurry up theme is the level theme played faster
+const HURRY_UP_SPEED: f32 = 1.25;
+// seconds for one track to fade out and the next to fade in
+const FADE_TIME: f32 = 1.0;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Music>()
+            .add_system(choose_track_system)
+            .add_system(fade_system.after(choose_track_system));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MusicTrack {
+    pub file: &'static str,
+    pub speed: f32,
+    pub repeat: bool,
+}
+
+impl MusicTrack {
+    fn looping(file: &'static str) -> Self {
+        Self { file, speed: 1., repeat: true }
+    }
+
+    pub fn level_theme(level: usize) -> Self {
+        MusicTrack::looping(LEVEL_MUSIC[level.saturating_sub(1) % LEVEL_MUSIC.len()])
+    }
+
+    // what should be playing right now, None for silence
+    pub fn for_state(state: GameState, level: usize, hurry_up: bool) -> Option<Self> {
+        match state {
+            GameState::StartMenu | GameState::HighScores | GameState::Settings | GameState::Editor
+                | GameState::LevelSelect => Some(MusicTrack::looping(MENU_MUSIC)),
+            GameState::Game if hurry_up => Some(MusicTrack { speed: HURRY_UP_SPEED, ..MusicTrack::level_theme(level) }),
+            GameState::Game => Some(MusicTrack::level_theme(level)),
+            GameState::GameOver => Some(MusicTrack { repeat: false, ..MusicTrack::looping(GAME_OVER_MUSIC) }),
+            // quiet, so the level clear fanfare stands out
+            GameState::LevelClear => None,
+        }
+    }
+}
+
+struct Channel {
+    track: MusicTrack,
+    sink: Handle<AudioSink>,
+    // 0 to 1, multiplied with the configured music volume
+    fade: f32,
+}
+
+// the track fading in or playing, and the ones on their way out
+#[derive(Resource, Default)]
+struct Music {
+    current: Option<Channel>,
+    fading_out: Vec<Channel>,
+}
+
+fn choose_track_system(
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    game_state: Res<State<GameState>>,
+    main_state: Option<Res<MainState>>,
+    timer: Option<Res<LevelTimer>>,
+    mut music: ResMut<Music>,
+) {
+    let level = main_state.map_or(1, |main_state| main_state.level);
+    let hurry_up = timer.is_some_and(|timer| timer.hurry_up());
+    let wanted = MusicTrack::for_state(*game_state.current(), level, hurry_up);
+
+    if music.current.as_ref().map(|channel| channel.track) == wanted {
+        return;
+    }
+
+    if let Some(channel) = music.current.take() {
+        music.fading_out.push(channel);
+    }
+
+    music.current = wanted.map(|track| {
+        let sink = audio.play_with_settings(
+            asset_server.load(track.file),
+            PlaybackSettings { repeat: track.repeat, volume: 0., speed: track.speed },
+        );
+
+        Channel { track, sink: audio_sinks.get_handle(sink), fade: 0. }
+    });
+}
+
+// moves every channel's fade towards where it should be and applies the volume settings
+fn fade_system(
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut music: ResMut<Music>,
+) {
+    let step = time.delta_seconds() / FADE_TIME;
+    let volume = config.music_level();
+    let music = &mut *music;
+
+    if let Some(channel) = music.current.as_mut() {
+        channel.fade = (channel.fade + step).min(1.);
+        if let Some(sink) = audio_sinks.get(&channel.sink) {
+            sink.set_volume(channel.fade * volume);
+        }
+    }
+
+    music.fading_out.retain_mut(|channel| {
+        channel.fade = (channel.fade - step).max(0.);
+
+        let Some(sink) = audio_sinks.get(&channel.sink) else {
+            // still loading, it has to be stopped once it starts
+            return true;
+        };
+
+        if channel.fade > 0. {
+            sink.set_volume(channel.fade * volume);
+            true
+        } else {
+            sink.stop();
+            false
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_tracks_by_state_level_and_time_left() {
+        let level_one = MusicTrack::for_state(GameState::Game, 1, false).unwrap();
+        let level_two = MusicTrack::for_state(GameState::Game, 2, false).unwrap();
+        let hurry_up = MusicTrack::for_state(GameState::Game, 1, true).unwrap();
+
+        assert_ne!(level_one.file, level_two.file);
+        assert_eq!(MusicTrack::for_state(GameState::Game, 3, false), Some(level_one));
+        assert_eq!(hurry_up.file, level_one.file);
+        assert!(hurry_up.speed > 1.);
+        assert!(!MusicTrack::for_state(GameState::GameOver, 1, false).unwrap().repeat);
+        assert_eq!(MusicTrack::for_state(GameState::LevelClear, 1, false), None);
+    }
+}
@@ -1,21 +1,56 @@
 use std::time::Duration;
 use rand::Rng;
 
-use crate::enemy::Enemy;
-use crate::tilemap::{TileCollider, Breakable};
+use crate::events::GameplayEvent;
+use crate::config::GameConfig;
+use crate::animation::{Animation, AnimationClip, AnimationMode};
+use crate::components::{Movable, Velocity};
+use crate::movement::{MovementIntegration, TileStep};
+use crate::collision::{Collider, Collision, CollisionDetection, Layer};
+use crate::tilemap::{Breakable, HidesExit, HidesPickup, Level, tile_to_world, spawn_exit};
+use crate::scoring::{ScoreEvent, PointsEarned, WALL_POINTS, CHAIN_BONUS};
+use crate::replay::GameRng;
 use crate::{
     GameTextures,
     TILE_SIZE,
-    BOMB_TIME,
-    PLAYER_SIZE, MainState,
-    GameState
+    MainState,
+    GameState,
+    EXTRA_LIFE_SCORE,
 };
 
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb::collide;
 
-pub const STARTING_BOMB_COUNT: usize = 5;
-pub const STARTING_HEALTH: f32 = 100.;
+pub const FUSE_TICK_INTERVAL: f32 = 0.5;
+
+// the player sheet has a row per facing direction, each with an idle frame, four walking
+// frames, a place bomb frame and a hurt frame, then a last row with the death frames
+const PLAYER_SHEET_COLUMNS: usize = 8;
+const PLAYER_SHEET_ROWS: usize = 5;
+const PLAYER_FRAME_SIZE: Vec2 = Vec2::new(16., 20.);
+
+// a bit smaller than a tile, so the player fits down corridors with some room to spare
+const PLAYER_COLLIDER: Vec2 = Vec2::new(TILE_SIZE * 0.8, TILE_SIZE * 0.8);
+
+// the contact box of the player and of every enemy. an enemy only hurts when both boxes
+// overlap, within 5/16 of a tile on both axes, so brushing past one in a corridor is safe
+pub const BODY_CONTACT: Vec2 = Vec2::new(TILE_SIZE * 0.3125, TILE_SIZE * 0.3125);
+
+// a pickup is collected once the player's centre is this close to it on both axes
+const PICKUP_REACH: Vec2 = Vec2::new(TILE_SIZE * 0.85, TILE_SIZE * 1.2);
+// sized so that it overlaps PLAYER_COLLIDER at exactly PICKUP_REACH
+const PICKUP_COLLIDER: Vec2 = Vec2::new(
+    PICKUP_REACH.x * 2. - PLAYER_COLLIDER.x,
+    PICKUP_REACH.y * 2. - PLAYER_COLLIDER.y,
+);
+
+// explosions reach into the tiles next to the bomb
+pub const EXPLOSION_SIZE: Vec2 = Vec2::new(TILE_SIZE * 2.5, TILE_SIZE * 2.5);
+
+const EXPLOSION_CLIP: AnimationClip = AnimationClip::new(0, 16, 0.05, AnimationMode::DespawnWhenDone);
+
+// how long a respawned player can't be hurt, blinking all the while
+const RESPAWN_INVULNERABILITY: f32 = 2.5;
+const BLINK_INTERVAL: f32 = 0.1;
 
 pub struct PlayerPlugin;
 
@@ -24,31 +59,138 @@ pub struct Player {
     speed: f32,
     pub num_bombs: usize,
     pub position: Vec2,
-    pub health: f32
+    pub health: f32,
+    pub power_ups: Vec<PowerUp>,
+    // points from this player's bombs on the current level
+    pub score: usize,
 }
 
 #[derive(Component)]
 pub struct Bomb {
     timer: Timer,
+    tick: Timer,
+    // the player who placed it, None for bombs restored from a save
+    owner: Option<Entity>,
+    // 0 for a bomb that went off on its own, otherwise how many bombs set each other off to reach it
+    chain: usize,
+}
+
+impl Bomb {
+    pub fn fuse_remaining(&self) -> Duration {
+        self.timer.remaining()
+    }
 }
 
 #[derive(Component)]
-pub struct Explosion;
+pub struct Explosion {
+    pub owner: Option<Entity>,
+    pub chain: usize,
+    // enemies this explosion has killed so far, for the multi kill multiplier
+    pub kills: usize,
+}
 
 #[derive(Component)]
-pub struct ExplosionToSpawn(pub Vec3);
+pub struct ExplosionToSpawn {
+    pub position: Vec3,
+    pub owner: Option<Entity>,
+    pub chain: usize,
+}
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Direction {
+    #[default]
+    Down,
+    Left,
+    Right,
+    Up,
+}
+
+// which way the player faces and whether they're walking, set from movement input
+#[derive(Component, Default)]
+pub struct Facing {
+    pub direction: Direction,
+    pub moving: bool,
+}
+
+impl Player {
+    // drops the most recently collected half of the power ups, undoing what they gave
+    fn lose_power_ups(&mut self) {
+        let kept = self.power_ups.len() / 2;
+        for power_up in self.power_ups.split_off(kept) {
+            match power_up {
+                PowerUp::ExtraBomb => self.num_bombs = self.num_bombs.saturating_sub(1),
+            }
+        }
+    }
+}
+
+// marks a player whose health ran out, once the death animation is over they respawn,
+// or the game ends if that was their last life
 #[derive(Component)]
-pub struct ExplosionTimer(pub Timer);
+struct Dying;
+
+// a freshly respawned player, enemies can't hurt them until the timer runs out
+#[derive(Component)]
+pub struct Invulnerable(Timer);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PlayerAction {
+    Idle,
+    Walk,
+    PlaceBomb,
+    Hurt,
+    Death,
+}
 
-impl Default for ExplosionTimer {
-    fn default() -> Self {
-        Self(Timer::from_seconds(0.05, TimerMode::Repeating))
+impl PlayerAction {
+    fn clip(&self, direction: Direction) -> AnimationClip {
+        let row = direction as usize * PLAYER_SHEET_COLUMNS;
+
+        match self {
+            PlayerAction::Idle => AnimationClip::new(row, 1, 1., AnimationMode::Loop),
+            PlayerAction::Walk => AnimationClip::new(row + 1, 4, 0.12, AnimationMode::Loop),
+            PlayerAction::PlaceBomb => AnimationClip::new(row + 5, 1, 0.2, AnimationMode::Once),
+            PlayerAction::Hurt => AnimationClip::new(row + 6, 1, 0.2, AnimationMode::Once),
+            PlayerAction::Death => AnimationClip::new(4 * PLAYER_SHEET_COLUMNS, 4, 0.15, AnimationMode::Once),
+        }
+    }
+}
+
+pub fn player_sheet(texture: Handle<Image>) -> TextureAtlas {
+    TextureAtlas::from_grid(texture, PLAYER_FRAME_SIZE, PLAYER_SHEET_COLUMNS, PLAYER_SHEET_ROWS, None, None)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PowerUp {
+    ExtraBomb,
+}
+
+impl PowerUp {
+    pub const ALL: [PowerUp; 1] = [PowerUp::ExtraBomb];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUp::ExtraBomb => "bomb",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<PowerUp> {
+        match name {
+            "bomb" => Some(PowerUp::ExtraBomb),
+            _ => None,
+        }
+    }
+
+    // pickups share the bomb sprite, tinted per power up
+    pub fn color(&self) -> Color {
+        match self {
+            PowerUp::ExtraBomb => Color::WHITE,
+        }
     }
 }
 
 #[derive(Component)]
-pub struct BombPickup;
+pub struct Pickup(pub PowerUp);
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
@@ -56,15 +198,19 @@ impl Plugin for PlayerPlugin {
         .add_system_set(SystemSet::on_enter(GameState::Game).with_system(player_spawn_system))
         .add_system_set(
             SystemSet::on_update(GameState::Game)
-                .with_system(player_movement_system)
+                .with_system(player_movement_system.before(MovementIntegration))
+                .with_system(player_moved_system.after(MovementIntegration))
                 .with_system(place_bomb_system)
                 .with_system(explode_bomb_system)
-                .with_system(camera_follow_system)
                 .with_system(explosion_to_spawn_system)
-                .with_system(explosion_animation_system)
-                .with_system(check_for_explosion_collision_system)
-                .with_system(enemy_collision_check)
-                .with_system(pickup_collision_check)
+                .with_system(player_animation_system.after(player_moved_system))
+                .with_system(check_for_explosion_collision_system.label(PointsEarned).after(CollisionDetection))
+                .with_system(chain_reaction_system.label(PointsEarned).after(CollisionDetection))
+                .with_system(enemy_collision_check.after(CollisionDetection))
+                .with_system(pickup_collision_check.after(CollisionDetection))
+                .with_system(player_death_system)
+                .with_system(invulnerability_system)
+                .with_system(extra_life_system)
         );
     }
 }
@@ -72,127 +218,194 @@ impl Plugin for PlayerPlugin {
 fn player_spawn_system(
     mut commands: Commands,
     game_textures: Res<GameTextures>,
+    level: Res<Level>,
+    config: Res<GameConfig>,
 ) {
-    commands.spawn(SpriteBundle {
-        texture: game_textures.player.clone(),
-        sprite: Sprite { 
-            custom_size: Some(Vec2::new(32.0, 64.0)), 
+    let (spawn_x, spawn_y) = level.player_spawn;
+    let spawn_position = tile_to_world(spawn_x, spawn_y);
 
+    let player = commands.spawn(SpriteSheetBundle {
+        texture_atlas: game_textures.player.clone(),
+        sprite: TextureAtlasSprite {
+            custom_size: Some(Vec2::new(32.0, 64.0)),
             ..Default::default()
         },
         transform: Transform {
-            translation: Vec3::new(128., -96., 10.),
+            translation: spawn_position.extend(10.),
             ..Default::default()
         },
         ..Default::default()
     })
     .insert(Player {
-        speed: 6.0, 
-        num_bombs: STARTING_BOMB_COUNT,
-        position: Vec2::new(400., 100.),
-        health: STARTING_HEALTH,
-    });
+        speed: config.player_speed,
+        num_bombs: config.starting_bombs,
+        position: spawn_position,
+        health: config.starting_health(),
+        power_ups: Vec::new(),
+        score: 0,
+    })
+    .insert(
+        Collider::new(PLAYER_COLLIDER, Layer::Player)
+            .with_mask(&[Layer::Enemy, Layer::Pickup])
+            .with_contact(BODY_CONTACT)
+    )
+    .insert(Velocity::default())
+    .insert(Movable::SOLID)
+    .insert(Facing::default())
+    .insert(Animation::new(PlayerAction::Idle.clip(Direction::Down)))
+    .id();
+
+    if config.strict_movement {
+        commands.entity(player).insert(TileStep::default());
+    }
 }
 
+// turns keyboard input into the player's velocity, the movement integrator moves them
 fn player_movement_system(
-    mut player_query: Query<(&mut Player, &mut Transform)>,
-    wall_query: Query<&Transform, (With<TileCollider>, Without<Player>)>,
+    mut player_query: Query<(&Player, &mut Velocity, &mut Facing, Option<&mut TileStep>)>,
     keyboard: Res<Input<KeyCode>>,
-    time: Res<Time>,
 ) {
-    let (mut player, mut transform) = player_query.single_mut();
+    let (player, mut velocity, mut facing, tile_step) = player_query.single_mut();
 
-    let mut y_delta = 0.0;
-    if keyboard.pressed(KeyCode::W) {
-        y_delta += player.speed * TILE_SIZE * time.delta_seconds();
-    } 
+    if player.health <= 0. {
+        velocity.set(Vec2::ZERO);
+        if let Some(mut tile_step) = tile_step {
+            tile_step.clear();
+        }
+        return;
+    }
 
+    let mut input = Vec2::ZERO;
+    if keyboard.pressed(KeyCode::W) {
+        input.y += 1.0;
+    }
     if keyboard.pressed(KeyCode::S) {
-        y_delta -= player.speed * TILE_SIZE * time.delta_seconds();
+        input.y -= 1.0;
     }
-
-    let mut x_delta = 0.0;
     if keyboard.pressed(KeyCode::A) {
-        x_delta -= player.speed * TILE_SIZE * time.delta_seconds();
+        input.x -= 1.0;
     }
     if keyboard.pressed(KeyCode::D) {
-        x_delta += player.speed * TILE_SIZE * time.delta_seconds();
+        input.x += 1.0;
     }
 
-    let target = transform.translation + Vec3::new(x_delta, 0.0, 0.0);
-    if wall_collision_check(target, &wall_query) {
-        transform.translation = target;
+    // sideways wins when moving diagonally, the sheet has no diagonal frames
+    if input.x != 0.0 {
+        facing.direction = if input.x < 0.0 { Direction::Left } else { Direction::Right };
+    } else if input.y != 0.0 {
+        facing.direction = if input.y < 0.0 { Direction::Down } else { Direction::Up };
     }
 
-    let target = transform.translation + Vec3::new(0.0, y_delta, 0.0);
-    if wall_collision_check(target, &wall_query) {
-        transform.translation = target;
-    }
-
-    player.position = Vec2::new(transform.translation.x, transform.translation.y);
+    velocity.set(input * player.speed * TILE_SIZE);
 }
 
-
-// camera script
-fn camera_follow_system(
-    player_query: Query<&Transform, With<Player>>,
-    mut camera_query: Query<&mut Transform, (Without<Player>, With<Camera>)>,
+// after the integrator has run, how far the player actually got
+fn player_moved_system(
+    mut player_query: Query<(&mut Player, &Transform, &Velocity, &mut Facing)>,
 ) {
-    let player_transform = player_query.single();
-    let mut camera_transform = camera_query.single_mut();
+    let (mut player, transform, velocity, mut facing) = player_query.single_mut();
 
-    camera_transform.translation.x = player_transform.translation.x;
-    camera_transform.translation.y = player_transform.translation.y;
+    facing.moving = velocity.vec() != Vec2::ZERO;
+    player.position = transform.translation.truncate();
 }
 
+
 fn place_bomb_system(
     mut commands: Commands,
     game_textures: Res<GameTextures>,
     keyboard: Res<Input<KeyCode>>,
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(Entity, &Transform), With<Player>>,
     mut player_component_query: Query<&mut Player>,
+    config: Res<GameConfig>,
+    mut events: EventWriter<GameplayEvent>,
 ) {
-    let player_transform = player_query.single();
+    let (player, player_transform) = player_query.single();
     
     let mut player_component = player_component_query.single_mut();
 
     let (bomb_x, bomb_y) = (player_transform.translation.x, player_transform.translation.y);
 
-    if keyboard.just_pressed(KeyCode::Return) && player_component.num_bombs > 0 {
-        commands.spawn(SpriteBundle{
-            texture: game_textures.bomb.clone(),    
-            sprite: Sprite{
-                custom_size: Some(Vec2::new(TILE_SIZE / 2., TILE_SIZE / 2.)),
-                ..Default::default()
-            },
-            transform: Transform {
-                translation: Vec3::new(bomb_x, bomb_y, 50.0),
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert(Bomb {
-            timer: Timer::new(Duration::from_secs(BOMB_TIME), TimerMode::Once)
-        });
+    if keyboard.just_pressed(KeyCode::Return) && player_component.num_bombs > 0 && player_component.health > 0. {
+        let fuse = Duration::from_secs_f32(config.bomb_fuse());
+        spawn_bomb(&mut commands, &game_textures, Vec2::new(bomb_x, bomb_y), fuse, Some(player));
 
         player_component.num_bombs -= 1;
+        events.send(GameplayEvent::BombPlaced { position: Vec2::new(bomb_x, bomb_y) });
     }
 }
 
+pub fn spawn_bomb(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    position: Vec2,
+    fuse: Duration,
+    owner: Option<Entity>,
+) {
+    commands.spawn(SpriteBundle{
+        texture: game_textures.bomb.clone(),
+        sprite: Sprite{
+            custom_size: Some(Vec2::new(TILE_SIZE / 2., TILE_SIZE / 2.)),
+            ..Default::default()
+        },
+        transform: Transform {
+            translation: position.extend(50.0),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Collider::new(Vec2::splat(TILE_SIZE / 2.), Layer::Bomb))
+    .insert(Velocity::default())
+    .insert(Movable::SOLID)
+    .insert(Bomb {
+        timer: Timer::new(fuse, TimerMode::Once),
+        tick: Timer::from_seconds(FUSE_TICK_INTERVAL, TimerMode::Repeating),
+        owner,
+        chain: 0,
+    });
+}
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    position: Vec2,
+    power_up: PowerUp,
+) {
+    commands.spawn(SpriteBundle{
+        texture: game_textures.bomb.clone(),
+        sprite: Sprite{
+            custom_size: Some(Vec2::new(TILE_SIZE / 2., TILE_SIZE / 2.)),
+            color: power_up.color(),
+            ..Default::default()
+        },
+        transform: Transform {
+            translation: position.extend(50.0),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Collider::new(PICKUP_COLLIDER, Layer::Pickup))
+    .insert(Pickup(power_up));
+}
+
 fn explode_bomb_system(
     mut commands: Commands,
     time: Res<Time>,
     mut bomb_query: Query<(Entity, &Transform, &mut Bomb)>,
+    mut events: EventWriter<GameplayEvent>,
 ) {
     for (entity, transform, mut bomb) in bomb_query.iter_mut() {
+        let position = transform.translation.truncate();
         bomb.timer.tick(time.delta());
 
         if bomb.timer.finished() {
             // spawn the explosion to spawn at bomb position:w
-            commands.spawn(ExplosionToSpawn(transform.translation.clone()));
+            commands.spawn(ExplosionToSpawn { position: transform.translation, owner: bomb.owner, chain: bomb.chain });
     
             commands.entity(entity).despawn();
 
+            events.send(GameplayEvent::Explosion { position, chain: bomb.chain });
+        } else if bomb.tick.tick(time.delta()).just_finished() {
+            events.send(GameplayEvent::FuseTick { position });
         }
     }
 }
@@ -206,141 +419,261 @@ fn explosion_to_spawn_system(
         commands.spawn(SpriteSheetBundle {
             texture_atlas: game_textures.explosion.clone(),
             transform: Transform {
-                translation: explosion_to_spawn.0,
+                translation: explosion_to_spawn.position,
                 ..Default::default()
             },
             ..Default::default()
         })
-        .insert(Explosion)
-        .insert(ExplosionTimer(Timer::from_seconds(0.05, TimerMode::Repeating)));
+        .insert(Explosion { owner: explosion_to_spawn.owner, chain: explosion_to_spawn.chain, kills: 0 })
+        .insert(Collider::new(EXPLOSION_SIZE, Layer::Explosion).with_mask(&[Layer::Breakable, Layer::Enemy, Layer::Bomb]))
+        .insert(Animation::new(EXPLOSION_CLIP));
 
         commands.entity(explosion_spawn_entity).despawn();
     }
 }
 
-fn explosion_animation_system(
-    mut commands: Commands,
-	time: Res<Time>,
-	mut query: Query<(Entity, &mut ExplosionTimer, &mut TextureAtlasSprite), With<Explosion>>,
+// picks the player's clip: dying beats everything, a hurt or place bomb frame plays out
+// unless something more important happens, otherwise walking or standing still
+fn player_animation_system(
+    mut events: EventReader<GameplayEvent>,
+    mut player_query: Query<(&Player, &Facing, &mut Animation)>,
 ) {
-    for (entity, mut timer, mut sprite) in query.iter_mut() {
-        timer.0.tick(time.delta());
-
-        if timer.0.finished() {
-            sprite.index += 1;
-
-            if sprite.index >= 16 {
-                commands.entity(entity).despawn();
-            }
+    let (mut hurt, mut placed_bomb) = (false, false);
+    for event in events.iter() {
+        match event {
+            GameplayEvent::PlayerHurt { .. } => hurt = true,
+            GameplayEvent::BombPlaced { .. } => placed_bomb = true,
+            _ => (),
         }
     }
+
+    let Ok((player, facing, mut animation)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    let clip = animation.clip();
+    let action = if player.health <= 0. {
+        PlayerAction::Death
+    } else if hurt {
+        PlayerAction::Hurt
+    } else if placed_bomb {
+        PlayerAction::PlaceBomb
+    } else if clip.mode == AnimationMode::Once && !animation.finished() {
+        return;
+    } else if facing.moving {
+        PlayerAction::Walk
+    } else {
+        PlayerAction::Idle
+    };
+
+    animation.play(action.clip(facing.direction));
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn check_for_explosion_collision_system(
     mut commands: Commands,
-    wall_query: Query<(Entity, &Transform), With<Breakable>>,
-    explosion_query: Query<&Transform, With<Explosion>>,
-    mut main_state: ResMut<MainState>,
+    mut collisions: EventReader<Collision>,
+    wall_query: Query<(&Transform, Option<&HidesExit>, Option<&HidesPickup>), With<Breakable>>,
+    explosion_query: Query<&Explosion>,
     game_textures: Res<GameTextures>,
+    config: Res<GameConfig>,
+    mut rng: ResMut<GameRng>,
+    mut events: EventWriter<GameplayEvent>,
+    mut scores: EventWriter<ScoreEvent>,
 ) {
-    for (entity, wall_transform) in wall_query.iter() {
-        for explosion_transform in explosion_query.iter() {
-            let explosion_translation = explosion_transform.translation;
-            let wall_translation = wall_transform.translation;
-
-            let collision = collide(
-                explosion_translation, 
-                Vec2::splat(TILE_SIZE * 2.5), 
-                wall_translation, 
-                Vec2::splat(TILE_SIZE) 
-            );
-
-            if collision.is_some() {
-                commands.entity(entity).despawn();
-                
-                main_state.score += 10;
-
-                // spawn a bomb pickup
-                let num = rand::thread_rng().gen_range(0..4);
-
-                if num == 0 {
-                    commands.spawn(SpriteBundle{
-                        texture: game_textures.bomb.clone(),    
-                        sprite: Sprite{
-                            custom_size: Some(Vec2::new(TILE_SIZE / 2., TILE_SIZE / 2.)),
-                            ..Default::default()
-                        },
-                        transform: Transform {
-                            translation: Vec3::new(wall_translation.x, wall_translation.y, 50.0),
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                    })
-                    .insert(BombPickup);
-                }
-            }
+    // a wall touched by several explosions only breaks once
+    let mut broken = Vec::new();
+
+    for collision in collisions.iter().filter(|collision| collision.layer == Layer::Breakable) {
+        let entity = collision.other;
+        let Ok((wall_transform, hides_exit, hides_pickup)) = wall_query.get(entity) else {
+            continue;
+        };
+        let wall_translation = wall_transform.translation;
+
+        if broken.contains(&entity) {
+            continue;
+        }
+
+        broken.push(entity);
+        commands.entity(entity).despawn();
+
+        let owner = explosion_query.get(collision.entity).ok().and_then(|explosion| explosion.owner);
+        scores.send(ScoreEvent { points: WALL_POINTS, owner, popup_at: None });
+        events.send(GameplayEvent::WallBroken { position: wall_translation.truncate() });
+
+        if hides_exit.is_some() {
+            spawn_exit(&mut commands, &game_textures, wall_translation.truncate());
+            continue;
+        }
+
+        if let Some(HidesPickup(power_up)) = hides_pickup {
+            spawn_pickup(&mut commands, &game_textures, wall_translation.truncate(), *power_up);
+            continue;
+        }
+
+        // spawn a bomb pickup
+        if rng.0.gen_bool(config.difficulty.settings().drop_chance as f64) {
+            spawn_pickup(&mut commands, &game_textures, wall_translation.truncate(), PowerUp::ExtraBomb);
         }
     }
-    
 }
 
-fn wall_collision_check(
-    target_player_pos: Vec3,
-    wall_query: &Query<&Transform, (With<TileCollider>, Without<Player>)>,
-) -> bool {
-    for wall_transform in wall_query.iter() {
-        let collision = collide(
-            target_player_pos,
-            Vec2::new(PLAYER_SIZE.0 * 0.7, PLAYER_SIZE.1 * 0.7),
-            wall_transform.translation,
-            Vec2::splat(TILE_SIZE),
-        );
-        if collision.is_some() {
-            return false;
+// an explosion touching a bomb sets it off straight away, and the owner of that explosion
+// gets a bonus that grows the further down the chain the bomb is
+fn chain_reaction_system(
+    mut collisions: EventReader<Collision>,
+    explosion_query: Query<&Explosion>,
+    mut bomb_query: Query<(&Transform, &mut Bomb)>,
+    mut scores: EventWriter<ScoreEvent>,
+) {
+    for collision in collisions.iter().filter(|collision| collision.layer == Layer::Bomb) {
+        let (Ok(explosion), Ok((transform, mut bomb))) = (explosion_query.get(collision.entity), bomb_query.get_mut(collision.other)) else {
+            continue;
+        };
+
+        // already set off by another explosion
+        if bomb.chain > 0 {
+            continue;
         }
+
+        bomb.chain = explosion.chain + 1;
+        let fuse = bomb.timer.duration();
+        bomb.timer.set_elapsed(fuse);
+
+        scores.send(ScoreEvent {
+            points: CHAIN_BONUS * bomb.chain,
+            owner: explosion.owner,
+            popup_at: Some(transform.translation.truncate()),
+        });
     }
-    true 
 }
 
 fn enemy_collision_check(
-    mut player_query: Query<(&Transform, &mut Player), With<Player>>,
-    enemy_query: Query<&Transform, With<Enemy>>,
+    mut collisions: EventReader<Collision>,
+    mut player_query: Query<&mut Player, (Without<Invulnerable>, Without<Dying>)>,
+    config: Res<GameConfig>,
+    mut events: EventWriter<GameplayEvent>,
 ) {
-    let (player_transform, mut player) = player_query.single_mut();
-
-    for enemy_transform in enemy_query.iter() {
-        let collision = collide(
-            player_transform.translation,
-            Vec2::new(20., 20.),
-            enemy_transform.translation,
-            Vec2::new(20., 20.)
-        );
-
-        if collision.is_some() {
-            player.health -= 5.;
-            println!("health: {}", player.health);
+    for collision in collisions.iter().filter(|collision| collision.layer == Layer::Enemy) {
+        let Ok(mut player) = player_query.get_mut(collision.entity) else {
+            continue;
+        };
+
+        // Dying is only added once player_death_system has seen the health run out
+        if player.health <= 0. {
+            continue;
         }
+
+        player.health -= config.enemy_damage();
+        events.send(GameplayEvent::PlayerHurt { damage: config.enemy_damage() });
     }
 }
 
 fn pickup_collision_check(
     mut commands: Commands,
-    mut player_query: Query<(&Transform, &mut Player),  With<Player>>,
-    pickup_query: Query<(Entity, &Transform), With<BombPickup>>,
+    mut collisions: EventReader<Collision>,
+    mut player_query: Query<&mut Player>,
+    pickup_query: Query<&Pickup>,
+    mut events: EventWriter<GameplayEvent>,
 ) {
-    let (player_transform, mut player) = player_query.single_mut();
-
-    for (entity, pickup_transform) in pickup_query.iter() {
-        let collision = collide(
-            player_transform.translation,
-            Vec2::new(PLAYER_SIZE.0 * 0.7, PLAYER_SIZE.1 * 0.7),
-            pickup_transform.translation,
-            Vec2::new(TILE_SIZE, TILE_SIZE)
-        );
+    // the same pickup can't be collected twice before its despawn goes through
+    let mut collected = Vec::new();
 
-        if collision.is_some() {
-            player.num_bombs += 1;
-            commands.entity(entity).despawn();
+    for collision in collisions.iter().filter(|collision| collision.layer == Layer::Pickup) {
+        let (Ok(mut player), Ok(pickup)) = (player_query.get_mut(collision.entity), pickup_query.get(collision.other)) else {
+            continue;
+        };
+
+        if collected.contains(&collision.other) {
+            continue;
+        }
+        collected.push(collision.other);
+
+        match pickup.0 {
+            PowerUp::ExtraBomb => player.num_bombs += 1,
+        }
+        player.power_ups.push(pickup.0);
+        commands.entity(collision.other).despawn();
+
+        events.send(GameplayEvent::PickupCollected { power_up: pickup.0 });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn player_death_system(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut Player, &mut Transform, &mut Animation, Option<&mut TileStep>, Option<&Dying>)>,
+    level: Res<Level>,
+    config: Res<GameConfig>,
+    mut main_state: ResMut<MainState>,
+    mut game_state: ResMut<State<GameState>>,
+    mut events: EventWriter<GameplayEvent>,
+) {
+    let (entity, mut player, mut transform, mut animation, tile_step, dying) = player_query.single_mut();
+
+    if player.health > 0. {
+        return;
+    }
+
+    if dying.is_none() {
+        commands.entity(entity).insert(Dying);
+        main_state.lives = main_state.lives.saturating_sub(1);
+        events.send(GameplayEvent::PlayerDeath);
+        return;
+    }
+
+    if animation.clip() != PlayerAction::Death.clip(Direction::Down) || !animation.finished() {
+        return;
+    }
+
+    if main_state.lives == 0 {
+        game_state.overwrite_set(GameState::GameOver).unwrap();
+        return;
+    }
+
+    // back at the spawn with full health and at least the bombs of a fresh start
+    let (spawn_x, spawn_y) = level.player_spawn;
+    let spawn_position = tile_to_world(spawn_x, spawn_y);
+    player.position = spawn_position;
+    transform.translation = spawn_position.extend(transform.translation.z);
+    if let Some(mut tile_step) = tile_step {
+        tile_step.clear();
+    }
+
+    player.health = config.starting_health();
+    player.lose_power_ups();
+    player.num_bombs = player.num_bombs.max(config.starting_bombs);
+    animation.play(PlayerAction::Idle.clip(Direction::Down));
+
+    commands.entity(entity)
+        .remove::<Dying>()
+        .insert(Invulnerable(Timer::from_seconds(RESPAWN_INVULNERABILITY, TimerMode::Once)));
+}
+
+// the player blinks while invulnerable
+fn invulnerability_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut player_query: Query<(Entity, &mut Invulnerable, &mut Visibility)>,
+) {
+    for (entity, mut invulnerable, mut visibility) in player_query.iter_mut() {
+        invulnerable.0.tick(time.delta());
+
+        let visible = invulnerable.0.finished() || (invulnerable.0.elapsed_secs() / BLINK_INTERVAL) as usize % 2 == 1;
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+
+        if invulnerable.0.finished() {
+            commands.entity(entity).remove::<Invulnerable>();
         }
     }
-}
\ No newline at end of file
+}
+
+fn extra_life_system(mut main_state: ResMut<MainState>) {
+    while main_state.score >= main_state.next_extra_life {
+        main_state.lives += 1;
+        main_state.next_extra_life += EXTRA_LIFE_SCORE;
+    }
+}
@@ -0,0 +1,232 @@
+use std::{fs::{self, File}, io::{self, LineWriter, Write}, path::Path};
+
+use bevy::{prelude::*, input::{InputPlugin, InputSystem}};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::GameState;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        // the keys go in after the real keyboard has been read, so just_pressed survives.
+        // headless runs have no InputPlugin and feed the keyboard by hand
+        if app.is_plugin_added::<InputPlugin>() {
+            app
+                .add_system_to_stage(CoreStage::PreUpdate, replay_input_system.after(InputSystem))
+                .add_system_to_stage(CoreStage::PreUpdate, record_input_system.after(replay_input_system));
+        } else {
+            app.add_system_to_stage(CoreStage::PreUpdate, replay_input_system);
+        }
+    }
+}
+
+// the random numbers gameplay draws on, like whether a broken wall drops a pickup. seeded
+// so that a replay plays out the same way as the run it was recorded from
+#[derive(Resource)]
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum KeyAction {
+    Press,
+    Release,
+}
+
+// scripted keyboard input for a game, one `<tick> press|release <key>` per line where the
+// tick counts frames since the game started. an optional `seed <n>` line seeds GameRng.
+// blank lines and lines starting with '#' are skipped
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub struct Replay {
+    inputs: Vec<(usize, KeyAction, KeyCode)>,
+    pub seed: Option<u64>,
+    tick: usize,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Replay> {
+        let source = fs::read_to_string(path)?;
+        Replay::parse(&source).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn parse(source: &str) -> Result<Replay, String> {
+        let mut inputs = Vec::new();
+        let mut seed = None;
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(value) = line.strip_prefix("seed ") {
+                seed = Some(value.trim().parse().map_err(|_| format!("line {}: {} is not a seed", number + 1, value))?);
+                continue;
+            }
+
+            let error = || format!("line {}: expected `<tick> press|release <key>`, got {}", number + 1, line);
+            let [tick, action, key] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+                return Err(error());
+            };
+
+            let tick = tick.parse().map_err(|_| error())?;
+            let action = match action {
+                "press" => KeyAction::Press,
+                "release" => KeyAction::Release,
+                _ => return Err(error()),
+            };
+            let key = key_from_name(key).ok_or_else(|| format!("line {}: unknown key {}", number + 1, key))?;
+
+            inputs.push((tick, action, key));
+        }
+
+        inputs.sort_by_key(|(tick, _, _)| *tick);
+        Ok(Replay { inputs, seed, tick: 0 })
+    }
+}
+
+// the keys the game itself listens to, by the names replay files use
+const KEYS: [(&str, KeyCode); 7] = [
+    ("W", KeyCode::W),
+    ("A", KeyCode::A),
+    ("S", KeyCode::S),
+    ("D", KeyCode::D),
+    ("Return", KeyCode::Return),
+    ("Escape", KeyCode::Escape),
+    ("F5", KeyCode::F5),
+];
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEYS.iter().find(|(key_name, _)| *key_name == name).map(|(_, key)| *key)
+}
+
+fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEYS.iter().find(|(_, known)| *known == key).map(|(name, _)| *name)
+}
+
+// writes the keys pressed during a game to a file that `--replay` can play back, counting
+// ticks the same way replay_input_system does
+#[derive(Resource)]
+pub struct Recorder {
+    file: LineWriter<File>,
+    tick: usize,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>, seed: u64) -> io::Result<Recorder> {
+        let mut file = LineWriter::new(File::create(path)?);
+        writeln!(file, "seed {}", seed)?;
+        Ok(Recorder { file, tick: 0 })
+    }
+
+    fn record(&mut self, action: &str, key: KeyCode) {
+        let Some(name) = key_name(key) else {
+            return;
+        };
+
+        if let Err(error) = writeln!(self.file, "{} {} {}", self.tick, action, name) {
+            warn!("could not record the replay: {}", error);
+        }
+    }
+}
+
+fn replay_input_system(
+    replay: Option<ResMut<Replay>>,
+    game_state: Res<State<GameState>>,
+    mut keyboard: ResMut<Input<KeyCode>>,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+    if *game_state.current() != GameState::Game {
+        return;
+    }
+
+    let tick = replay.tick;
+    for (_, action, key) in replay.inputs.iter().filter(|(at, _, _)| *at == tick) {
+        match action {
+            KeyAction::Press => keyboard.press(*key),
+            KeyAction::Release => keyboard.release(*key),
+        }
+    }
+    replay.tick += 1;
+}
+
+fn record_input_system(
+    recorder: Option<ResMut<Recorder>>,
+    game_state: Res<State<GameState>>,
+    keyboard: Res<Input<KeyCode>>,
+) {
+    let Some(mut recorder) = recorder else {
+        return;
+    };
+    if *game_state.current() != GameState::Game {
+        return;
+    }
+
+    for key in keyboard.get_just_pressed() {
+        recorder.record("press", *key);
+    }
+    for key in keyboard.get_just_released() {
+        recorder.record("release", *key);
+    }
+    recorder.tick += 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::GameConfig, headless::HeadlessApp};
+
+    #[test]
+    fn replays_key_presses_on_their_ticks() {
+        assert!(Replay::parse("5 press Q").is_err());
+        assert!(Replay::parse("five press W").is_err());
+
+        let replay = Replay::parse("\
+# walk right for a bit, then drop a bomb
+1 press D
+30 release D
+31 press Return
+32 release Return
+").unwrap();
+
+        let mut game = HeadlessApp::new("\
+#######
+#P....#
+#######");
+        game.world().insert_resource(replay);
+        let start = game.player().position;
+
+        game.run(40);
+
+        assert!(game.player().position.x > start.x);
+        assert_eq!(game.player().num_bombs, GameConfig::default().starting_bombs - 1);
+    }
+
+    #[test]
+    fn recordings_play_back_as_replays() {
+        assert!(Replay::parse("seed x").is_err());
+
+        let path = std::env::temp_dir().join(format!("bomberman-recording-{}.txt", std::process::id()));
+        let mut recorder = Recorder::create(&path, 42).unwrap();
+        recorder.tick = 3;
+        recorder.record("press", KeyCode::D);
+        // not a key the game listens to
+        recorder.record("press", KeyCode::Q);
+        recorder.tick = 9;
+        recorder.record("release", KeyCode::D);
+        drop(recorder);
+
+        let replay = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(replay.seed, Some(42));
+        assert_eq!(replay.inputs, vec![(3, KeyAction::Press, KeyCode::D), (9, KeyAction::Release, KeyCode::D)]);
+    }
+}
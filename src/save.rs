@@ -0,0 +1,456 @@
+use std::{fmt::Write, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    GameTextures, GameState, MainState, STARTING_LIVES, storage,
+    level_timer::LevelTimer,
+    stats::{Stats, LevelStats},
+    enemy::{self, Enemy, EnemyKind},
+    player::{self, Player, Bomb, Pickup, PowerUp},
+    tilemap::{Level, Tile, Breakable, HidesPickup, world_to_tile},
+};
+
+const SAVE_FILE: &str = "savegame.txt";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_system_set(
+                SystemSet::on_update(GameState::Game)
+                    .with_system(save_game_system)
+                    .with_system(restore_game_system)
+            );
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerSave {
+    pub position: Vec2,
+    pub health: f32,
+    pub num_bombs: usize,
+    pub power_ups: Vec<PowerUp>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnemySave {
+    pub kind: EnemyKind,
+    pub position: Vec2,
+    pub speed: f32,
+    pub health: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BombSave {
+    pub position: Vec2,
+    pub fuse: Duration,
+}
+
+// a snapshot of a game in progress. the level keeps its player spawn but drops the enemy
+// spawns, the enemies that are still alive are stored with their current positions instead
+#[derive(Clone, Debug)]
+pub struct SaveGame {
+    pub score: usize,
+    pub level_number: usize,
+    pub lives: usize,
+    pub time_left: f32,
+    // the run's stats up to the level being played, and the seconds spent on it so far
+    pub stats: Stats,
+    pub level_time: f32,
+    pub level: Level,
+    pub player: PlayerSave,
+    pub enemies: Vec<EnemySave>,
+    pub bombs: Vec<BombSave>,
+    pub pickups: Vec<(Vec2, PowerUp)>,
+}
+
+// inserted when a save is loaded, restore_game_system applies it once the level has spawned
+#[derive(Resource)]
+pub struct PendingRestore(pub SaveGame);
+
+impl SaveGame {
+    pub fn exists() -> bool {
+        matches!(storage::read(SAVE_FILE), Ok(Some(_)))
+    }
+
+    pub fn load() -> Result<SaveGame, String> {
+        match storage::read(SAVE_FILE) {
+            Ok(Some(contents)) => SaveGame::parse(&contents),
+            Ok(None) => Err("there is no saved game".to_string()),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        storage::write(SAVE_FILE, &self.to_file_string())
+    }
+
+    pub fn to_file_string(&self) -> String {
+        let mut out = String::new();
+        let player = &self.player;
+
+        writeln!(out, "[state]").unwrap();
+        writeln!(out, "score {}", self.score).unwrap();
+        writeln!(out, "level {}", self.level.name).unwrap();
+        writeln!(out, "level_number {}", self.level_number).unwrap();
+        writeln!(out, "lives {}", self.lives).unwrap();
+        writeln!(out, "time_left {}", self.time_left).unwrap();
+        writeln!(out, "level_time {}", self.level_time).unwrap();
+
+        writeln!(out, "[stats]").unwrap();
+        self.stats.write(&mut out);
+
+        writeln!(out, "[player]").unwrap();
+        writeln!(out, "position {} {}", player.position.x, player.position.y).unwrap();
+        writeln!(out, "health {}", player.health).unwrap();
+        writeln!(out, "bombs {}", player.num_bombs).unwrap();
+        let power_ups: Vec<&str> = player.power_ups.iter().map(PowerUp::name).collect();
+        writeln!(out, "powerups {}", power_ups.join(" ")).unwrap();
+
+        writeln!(out, "[enemies]").unwrap();
+        for enemy in self.enemies.iter() {
+            writeln!(
+                out, "{} {} {} {} {}",
+                enemy.position.x, enemy.position.y, enemy.speed, enemy.health, enemy.kind.name()
+            ).unwrap();
+        }
+
+        writeln!(out, "[bombs]").unwrap();
+        for bomb in self.bombs.iter() {
+            writeln!(out, "{} {} {}", bomb.position.x, bomb.position.y, bomb.fuse.as_secs_f32()).unwrap();
+        }
+
+        writeln!(out, "[pickups]").unwrap();
+        for (position, power_up) in self.pickups.iter() {
+            writeln!(out, "{} {} {}", position.x, position.y, power_up.name()).unwrap();
+        }
+
+        writeln!(out, "[map]").unwrap();
+        writeln!(out, "{}", self.level.to_map_string()).unwrap();
+
+        out
+    }
+
+    pub fn parse(contents: &str) -> Result<SaveGame, String> {
+        let mut section = "";
+        let mut score = None;
+        let mut level_number = 1;
+        // saves from before lives get the usual number
+        let mut lives = STARTING_LIVES;
+        let mut time_left = None;
+        // and saves from before stats start counting afresh
+        let mut stats = Stats::default();
+        let mut level_time = 0.;
+        let mut level_name = String::new();
+        let mut position = None;
+        let mut health = None;
+        let mut num_bombs = None;
+        let mut power_ups = Vec::new();
+        let mut enemies = Vec::new();
+        let mut bombs = Vec::new();
+        let mut pickups = Vec::new();
+        let mut map = String::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            let error = |message: &str| format!("line {}: {}", index + 1, message);
+
+            if line.starts_with('[') && line.ends_with(']') {
+                section = &line[1..line.len() - 1];
+                continue;
+            }
+
+            if section == "map" {
+                map.push_str(line);
+                map.push('\n');
+                continue;
+            }
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (key, rest) = line.split_once(' ').unwrap_or((line, ""));
+
+            match (section, key) {
+                ("state", "score") => score = Some(rest.parse().map_err(|_| error("bad score"))?),
+                ("state", "level") => level_name = rest.to_string(),
+                ("state", "level_number") => level_number = rest.parse().map_err(|_| error("bad level number"))?,
+                ("state", "lives") => lives = rest.parse().map_err(|_| error("bad lives"))?,
+                ("state", "time_left") => time_left = Some(rest.parse().map_err(|_| error("bad time"))?),
+                ("state", "level_time") => level_time = rest.parse().map_err(|_| error("bad level time"))?,
+                ("stats", _) => {
+                    if !stats.read_line(key, rest) {
+                        return Err(error("bad stat"));
+                    }
+                },
+                ("player", "position") => match numbers(rest)[..] {
+                    [x, y] => position = Some(Vec2::new(x, y)),
+                    _ => return Err(error("bad position")),
+                },
+                ("player", "health") => health = Some(rest.parse().map_err(|_| error("bad health"))?),
+                ("player", "bombs") => num_bombs = Some(rest.parse().map_err(|_| error("bad bomb count"))?),
+                ("player", "powerups") => {
+                    power_ups = rest.split_whitespace()
+                        .map(PowerUp::from_name)
+                        .collect::<Option<_>>()
+                        .ok_or_else(|| error("unknown power up"))?;
+                },
+                ("enemies", _) => {
+                    let (values, name) = line.rsplit_once(' ').ok_or_else(|| error("expected x y speed health kind"))?;
+                    match (&numbers(values)[..], EnemyKind::from_name(name)) {
+                        (&[x, y, speed, health], Some(kind)) if health >= 1. => enemies.push(EnemySave {
+                            kind,
+                            position: Vec2::new(x, y),
+                            speed,
+                            health: health as u32,
+                        }),
+                        _ => return Err(error("expected x y speed health kind")),
+                    }
+                },
+                ("bombs", _) => match numbers(line)[..] {
+                    [x, y, fuse] if fuse >= 0. => bombs.push(BombSave {
+                        position: Vec2::new(x, y),
+                        fuse: Duration::from_secs_f32(fuse),
+                    }),
+                    _ => return Err(error("expected x y fuse")),
+                },
+                ("pickups", _) => {
+                    let (coordinates, name) = line.rsplit_once(' ').ok_or_else(|| error("expected x y power up"))?;
+                    match (&numbers(coordinates)[..], PowerUp::from_name(name)) {
+                        (&[x, y], Some(power_up)) => pickups.push((Vec2::new(x, y), power_up)),
+                        _ => return Err(error("expected x y power up")),
+                    }
+                },
+                _ => return Err(error("unexpected line")),
+            }
+        }
+
+        let level = Level::parse(&level_name, &map);
+        if level.tiles.is_empty() {
+            return Err("the save has no map".to_string());
+        }
+        let time_left = time_left.unwrap_or(level.time_limit as f32);
+
+        Ok(SaveGame {
+            score: score.ok_or("the save has no score")?,
+            level_number,
+            lives,
+            time_left,
+            stats,
+            level_time,
+            level,
+            player: PlayerSave {
+                position: position.ok_or("the save has no player position")?,
+                health: health.ok_or("the save has no player health")?,
+                num_bombs: num_bombs.ok_or("the save has no bomb count")?,
+                power_ups,
+            },
+            enemies,
+            bombs,
+            pickups,
+        })
+    }
+}
+
+// the whitespace separated numbers on a line, empty if any of them doesn't parse
+fn numbers(text: &str) -> Vec<f32> {
+    text.split_whitespace()
+        .map(|field| field.parse().ok())
+        .collect::<Option<_>>()
+        .unwrap_or_default()
+}
+
+// F5 saves the game in progress
+#[allow(clippy::too_many_arguments)]
+fn save_game_system(
+    keyboard: Res<Input<KeyCode>>,
+    level: Res<Level>,
+    main_state: Res<MainState>,
+    timer: Res<LevelTimer>,
+    level_stats: Res<LevelStats>,
+    player_query: Query<&Player>,
+    wall_query: Query<(&Transform, Option<&HidesPickup>), With<Breakable>>,
+    enemy_query: Query<(&Transform, &Enemy)>,
+    bomb_query: Query<(&Transform, &Bomb)>,
+    pickup_query: Query<(&Transform, &Pickup)>,
+) {
+    if !keyboard.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    let player = player_query.single();
+
+    // breakable walls that are gone from the world have been blown up
+    let mut saved_level = level.clone();
+    for row in saved_level.tiles.iter_mut() {
+        for tile in row.iter_mut() {
+            *tile = match tile {
+                Tile::Breakable => Tile::Floor,
+                Tile::HiddenExit => Tile::Exit,
+                _ => *tile,
+            };
+        }
+    }
+    // pickups still hidden stay in the map, the ones lying around are saved with the rest
+    saved_level.pickups.clear();
+    for (transform, hides_pickup) in wall_query.iter() {
+        let (x, y) = world_to_tile(transform.translation.truncate());
+        let tile = &mut saved_level.tiles[y][x];
+        *tile = if *tile == Tile::Exit { Tile::HiddenExit } else { Tile::Breakable };
+
+        if let Some(HidesPickup(power_up)) = hides_pickup {
+            saved_level.pickups.push(((x, y), *power_up));
+        }
+    }
+    saved_level.enemy_spawns.clear();
+
+    let save_game = SaveGame {
+        score: main_state.score,
+        level_number: main_state.level,
+        lives: main_state.lives,
+        time_left: timer.remaining,
+        stats: main_state.stats.clone(),
+        level_time: level_stats.time,
+        level: saved_level,
+        player: PlayerSave {
+            position: player.position,
+            health: player.health,
+            num_bombs: player.num_bombs,
+            power_ups: player.power_ups.clone(),
+        },
+        enemies: enemy_query
+            .iter()
+            .map(|(transform, enemy)| EnemySave {
+                kind: enemy.kind,
+                position: transform.translation.truncate(),
+                speed: enemy.speed,
+                health: enemy.health,
+            })
+            .collect(),
+        bombs: bomb_query
+            .iter()
+            .map(|(transform, bomb)| BombSave { position: transform.translation.truncate(), fuse: bomb.fuse_remaining() })
+            .collect(),
+        pickups: pickup_query
+            .iter()
+            .map(|(transform, pickup)| (transform.translation.truncate(), pickup.0))
+            .collect(),
+    };
+
+    match save_game.save() {
+        Ok(()) => info!("game saved"),
+        Err(error) => warn!("could not save the game: {}", error),
+    }
+}
+
+fn restore_game_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    pending: Option<Res<PendingRestore>>,
+    mut timer: ResMut<LevelTimer>,
+    mut level_stats: ResMut<LevelStats>,
+    mut player_query: Query<(&mut Player, &mut Transform)>,
+) {
+    let Some(pending) = pending else {
+        return;
+    };
+
+    // wait for player_spawn_system's commands to land
+    let Ok((mut player, mut transform)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    let save_game = &pending.0;
+
+    timer.remaining = save_game.time_left;
+    level_stats.time = save_game.level_time;
+
+    player.position = save_game.player.position;
+    player.health = save_game.player.health;
+    player.num_bombs = save_game.player.num_bombs;
+    player.power_ups = save_game.player.power_ups.clone();
+    transform.translation = save_game.player.position.extend(transform.translation.z);
+
+    for saved in save_game.enemies.iter() {
+        let mut enemy = Enemy::new(saved.kind);
+        enemy.speed = saved.speed;
+        enemy.health = saved.health;
+
+        enemy::spawn_enemy(&mut commands, &game_textures, saved.position, enemy);
+    }
+
+    for saved in save_game.bombs.iter() {
+        player::spawn_bomb(&mut commands, &game_textures, saved.position, saved.fuse, None);
+    }
+
+    for (position, power_up) in save_game.pickups.iter() {
+        player::spawn_pickup(&mut commands, &game_textures, *position, *power_up);
+    }
+
+    commands.remove_resource::<PendingRestore>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::GameMode;
+
+    #[test]
+    fn save_file_round_trips() {
+        let mut level = Level::parse("map", "time=120\nmode=battle\n#####\n#P.@#\n#x@E#\n#####");
+        level.enemy_spawns.clear();
+
+        let save_game = SaveGame {
+            score: 230,
+            level_number: 2,
+            lives: 1,
+            time_left: 93.5,
+            stats: Stats {
+                bombs_placed: 7,
+                damage_taken: 12.5,
+                level_times: vec![("01-warm-up".to_string(), 61.25)],
+                ..Stats::default()
+            },
+            level_time: 26.5,
+            level,
+            player: PlayerSave {
+                position: Vec2::new(70.5, -64.),
+                health: 42.5,
+                num_bombs: 3,
+                power_ups: vec![PowerUp::ExtraBomb, PowerUp::ExtraBomb],
+            },
+            enemies: vec![EnemySave { kind: EnemyKind::Ogre, position: Vec2::new(100., -90.), speed: 0.8, health: 2 }],
+            bombs: vec![BombSave { position: Vec2::new(64., -64.), fuse: Duration::from_millis(1250) }],
+            pickups: vec![(Vec2::new(128., -128.), PowerUp::ExtraBomb)],
+        };
+
+        let loaded = SaveGame::parse(&save_game.to_file_string()).unwrap();
+
+        assert_eq!(loaded.score, 230);
+        assert_eq!(loaded.level_number, 2);
+        assert_eq!(loaded.lives, 1);
+        assert_eq!(loaded.time_left, 93.5);
+        assert_eq!(loaded.stats, save_game.stats);
+        assert_eq!(loaded.level_time, 26.5);
+        assert_eq!(loaded.level.name, "map");
+        assert_eq!(loaded.level.tiles, save_game.level.tiles);
+        assert_eq!(loaded.level.player_spawn, (1, 1));
+        assert_eq!(loaded.level.time_limit, 120);
+        assert_eq!(loaded.level.mode, GameMode::Battle);
+        assert_eq!(loaded.level.tiles[2][1], Tile::HiddenExit);
+        assert!(loaded.level.enemy_spawns.is_empty());
+        assert_eq!(loaded.player, save_game.player);
+        assert_eq!(loaded.enemies, save_game.enemies);
+        assert_eq!(loaded.bombs, save_game.bombs);
+        assert_eq!(loaded.pickups, save_game.pickups);
+    }
+
+    #[test]
+    fn rejects_damaged_saves() {
+        assert!(SaveGame::parse("").is_err());
+        assert!(SaveGame::parse("[state]\nscore lots\n").is_err());
+        assert!(SaveGame::parse("[state]\nscore 1\n[player]\nposition 1\n").is_err());
+    }
+}
@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+
+use crate::{
+    GameState, GameTextures, MainState,
+    enemy::EnemyKind,
+    player::Player,
+};
+
+pub const WALL_POINTS: usize = 10;
+// for each bomb set off by another explosion, times how far down the chain it is
+pub const CHAIN_BONUS: usize = 50;
+pub const TIME_BONUS_PER_SECOND: usize = 10;
+// the multi kill multiplier doubles with each kill from the same bomb, up to this
+const MAX_MULTIPLIER: usize = 8;
+
+// how long score popups float, and how far they rise in that time
+const POPUP_TIME: f32 = 0.8;
+const POPUP_RISE: f32 = 40.;
+const POPUP_COLOR: Color = Color::rgb(1.0, 0.9, 0.4);
+
+pub struct ScoringPlugin;
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<ScoreEvent>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Game)
+                    .with_system(award_score_system.after(PointsEarned))
+                    .with_system(score_popup_system)
+            );
+    }
+}
+
+// the systems that send ScoreEvents, so they're all counted the frame they're sent. a state
+// change that frame would otherwise leave the later ones unread
+#[derive(SystemLabel)]
+pub struct PointsEarned;
+
+// points earned during play. the owner is the player whose bomb earned them, if any
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScoreEvent {
+    pub points: usize,
+    pub owner: Option<Entity>,
+    // where to show the points floating up, None for no popup
+    pub popup_at: Option<Vec2>,
+}
+
+pub fn enemy_points(kind: EnemyKind) -> usize {
+    match kind {
+        EnemyKind::Bat => 100,
+        EnemyKind::Ogre => 300,
+    }
+}
+
+// the first kill from a bomb counts once, the second twice, the third four times and so on
+pub fn multi_kill_multiplier(kill: usize) -> usize {
+    (1..kill).fold(1, |multiplier, _| (multiplier * 2).min(MAX_MULTIPLIER))
+}
+
+pub fn time_bonus(seconds_left: f32) -> usize {
+    seconds_left.ceil() as usize * TIME_BONUS_PER_SECOND
+}
+
+#[derive(Component)]
+struct ScorePopup {
+    timer: Timer,
+    start: Vec2,
+}
+
+fn award_score_system(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+    mut scores: EventReader<ScoreEvent>,
+    mut main_state: ResMut<MainState>,
+    mut player_query: Query<&mut Player>,
+) {
+    for score in scores.iter() {
+        main_state.score += score.points;
+
+        // the owner may have been despawned since, the points still count for the run
+        if let Some(mut player) = score.owner.and_then(|owner| player_query.get_mut(owner).ok()) {
+            player.score += score.points;
+        }
+
+        if let Some(position) = score.popup_at {
+            commands.spawn(Text2dBundle {
+                text: Text::from_section(
+                    score.points.to_string(),
+                    TextStyle { font: game_textures.font.clone(), font_size: 24., color: POPUP_COLOR },
+                ),
+                transform: Transform::from_translation(position.extend(200.)),
+                ..default()
+            })
+            .insert(ScorePopup {
+                timer: Timer::from_seconds(POPUP_TIME, TimerMode::Once),
+                start: position,
+            });
+        }
+    }
+}
+
+// popups drift up and fade out
+fn score_popup_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut popup_query: Query<(Entity, &mut ScorePopup, &mut Transform, &mut Text)>,
+) {
+    for (entity, mut popup, mut transform, mut text) in popup_query.iter_mut() {
+        popup.timer.tick(time.delta());
+        if popup.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let progress = popup.timer.percent();
+        transform.translation.y = popup.start.y + POPUP_RISE * progress;
+        text.sections[0].style.color.set_a(1. - progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multi_kills_double_up_to_the_cap() {
+        let multipliers: Vec<usize> = (1..=6).map(multi_kill_multiplier).collect();
+        assert_eq!(multipliers, [1, 2, 4, 8, 8, 8]);
+
+        assert_eq!(enemy_points(EnemyKind::Ogre) * multi_kill_multiplier(2), 600);
+        assert_eq!(time_bonus(41.2), 420);
+    }
+}
@@ -0,0 +1,229 @@
+use bevy::{prelude::*, window::WindowMode};
+
+use crate::{
+    GameTextures, GameState,
+    config::{GameConfig, VOLUME_STEP},
+    ui::{screen_root, text_style, despawn_screen},
+};
+
+// window sizes the settings screen cycles through
+const RESOLUTIONS: [(f32, f32); 5] = [(640., 640.), (800., 800.), (960., 960.), (1280., 720.), (1920., 1080.)];
+
+const SELECTED_COLOR: Color = Color::rgb(1.0, 0.85, 0.3);
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SelectedSetting>()
+            .add_system(apply_window_settings)
+            .add_system_set(SystemSet::on_enter(GameState::Settings).with_system(spawn_settings_screen))
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings)
+                    .with_system(settings_input_system)
+                    .with_system(update_settings_screen.after(settings_input_system))
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Settings).with_system(despawn_screen));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Setting {
+    Resolution,
+    Fullscreen,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    Difficulty,
+    Movement,
+}
+
+impl Setting {
+    const ALL: [Setting; 7] = [
+        Setting::Resolution,
+        Setting::Fullscreen,
+        Setting::MasterVolume,
+        Setting::MusicVolume,
+        Setting::SfxVolume,
+        Setting::Difficulty,
+        Setting::Movement,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Setting::Resolution => "Resolution",
+            Setting::Fullscreen => "Fullscreen",
+            Setting::MasterVolume => "Master Volume",
+            Setting::MusicVolume => "Music Volume",
+            Setting::SfxVolume => "Effects Volume",
+            Setting::Difficulty => "Difficulty",
+            Setting::Movement => "Movement",
+        }
+    }
+
+    fn value(&self, config: &GameConfig) -> String {
+        let percent = |volume: f32| format!("{:.0}%", volume * 100.);
+
+        match self {
+            Setting::Resolution => format!("{}x{}", config.window_width, config.window_height),
+            Setting::Fullscreen => if config.fullscreen { "On" } else { "Off" }.to_string(),
+            Setting::MasterVolume => percent(config.master_volume),
+            Setting::MusicVolume => percent(config.music_volume),
+            Setting::SfxVolume => percent(config.sfx_volume),
+            Setting::Difficulty => config.difficulty.name().to_string(),
+            Setting::Movement => if config.strict_movement { "Tile by tile" } else { "Free" }.to_string(),
+        }
+    }
+
+    // changes the setting one step left (-1) or right (1), returning the config keys it touched
+    fn change(&self, config: &mut GameConfig, step: isize) -> &'static [&'static str] {
+        let volume = |volume: &mut f32| *volume = (*volume + step as f32 * VOLUME_STEP).clamp(0., 1.);
+
+        match self {
+            Setting::Resolution => {
+                let current = RESOLUTIONS
+                    .iter()
+                    .position(|size| *size == (config.window_width, config.window_height))
+                    .unwrap_or(0) as isize;
+                let (width, height) = RESOLUTIONS[(current + step).rem_euclid(RESOLUTIONS.len() as isize) as usize];
+
+                config.window_width = width;
+                config.window_height = height;
+                &["window_width", "window_height"]
+            },
+            Setting::Fullscreen => {
+                config.fullscreen = !config.fullscreen;
+                &["fullscreen"]
+            },
+            Setting::MasterVolume => {
+                volume(&mut config.master_volume);
+                &["master_volume"]
+            },
+            Setting::MusicVolume => {
+                volume(&mut config.music_volume);
+                &["music_volume"]
+            },
+            Setting::SfxVolume => {
+                volume(&mut config.sfx_volume);
+                &["sfx_volume"]
+            },
+            Setting::Difficulty => {
+                config.difficulty = config.difficulty.cycle(step);
+                &["difficulty"]
+            },
+            Setting::Movement => {
+                config.strict_movement = !config.strict_movement;
+                &["strict_movement"]
+            },
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct SelectedSetting(usize);
+
+// one line of text per setting, in the same order as Setting::ALL
+#[derive(Component)]
+struct SettingText(Setting);
+
+fn spawn_settings_screen(
+    mut commands: Commands,
+    game_textures: Res<GameTextures>,
+) {
+    let root = screen_root(&mut commands);
+
+    commands.entity(root).with_children(|parent| {
+        parent.spawn(TextBundle::from_section("Settings", text_style(&game_textures, 60.)));
+
+        for setting in Setting::ALL {
+            parent.spawn(TextBundle::from_section("", text_style(&game_textures, 30.)))
+                .insert(SettingText(setting));
+        }
+
+        parent.spawn(TextBundle::from_section(
+            "Up/Down: choose   Left/Right: change   Enter: back",
+            text_style(&game_textures, 20.),
+        ));
+    });
+}
+
+fn settings_input_system(
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut selected: ResMut<SelectedSetting>,
+    mut config: ResMut<GameConfig>,
+    mut game_state: ResMut<State<GameState>>,
+) {
+    let count = Setting::ALL.len();
+
+    if keyboard.just_pressed(KeyCode::Down) {
+        selected.0 = (selected.0 + 1) % count;
+    }
+    if keyboard.just_pressed(KeyCode::Up) {
+        selected.0 = (selected.0 + count - 1) % count;
+    }
+
+    let step = if keyboard.just_pressed(KeyCode::Right) {
+        1
+    } else if keyboard.just_pressed(KeyCode::Left) {
+        -1
+    } else {
+        0
+    };
+
+    if step != 0 {
+        let keys = Setting::ALL[selected.0].change(&mut config, step);
+        config.save(keys);
+    }
+
+    if keyboard.just_pressed(KeyCode::Return) || keyboard.just_pressed(KeyCode::Escape) {
+        // the menu runs this same frame, and shouldn't see the key that opened it
+        keyboard.reset(KeyCode::Return);
+        game_state.set(GameState::StartMenu).unwrap();
+    }
+}
+
+fn update_settings_screen(
+    config: Res<GameConfig>,
+    selected: Res<SelectedSetting>,
+    mut text_query: Query<(&mut Text, &SettingText)>,
+) {
+    for (mut text, SettingText(setting)) in text_query.iter_mut() {
+        let is_selected = Setting::ALL[selected.0] == *setting;
+
+        text.sections[0].value = format!("{}: < {} >", setting.label(), setting.value(&config));
+        text.sections[0].style.color = if is_selected { SELECTED_COLOR } else { Color::rgb(0.9, 0.9, 0.9) };
+    }
+}
+
+// only the window settings that changed since last time are applied, so a window resized by
+// hand keeps its size through F11 and changes to any other setting
+fn apply_window_settings(
+    config: Res<GameConfig>,
+    mut windows: ResMut<Windows>,
+    mut applied: Local<Option<(f32, f32, bool)>>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+
+    let (width, height, fullscreen) = (config.window_width, config.window_height, config.fullscreen);
+    let previous = applied.replace((width, height, fullscreen));
+
+    if previous.map(|(_, _, fullscreen)| fullscreen) != Some(fullscreen) {
+        let mode = if fullscreen { WindowMode::BorderlessFullscreen } else { WindowMode::Windowed };
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+    }
+
+    if previous.map(|(width, height, _)| (width, height)) != Some((width, height))
+        && (window.requested_width() != width || window.requested_height() != height)
+    {
+        window.set_resolution(width, height);
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{events::GameplayEvent, config::GameConfig};
+
+pub struct SfxPlugin;
+
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PlayingSounds>()
+            .add_startup_system(load_sound_effects)
+            .add_system(play_sound_effects_system);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SoundEffect {
+    BombPlaced,
+    FuseTick,
+    Explosion,
+    WallBroken,
+    EnemyKilled,
+    Pickup,
+    PlayerHurt,
+    PlayerDeath,
+    LevelClear,
+}
+
+pub struct SoundSpec {
+    pub file: &'static str,
+    pub volume: f32,
+    // playback speed is picked at random within 1 +/- this, so repeats don't sound identical
+    pub pitch_variation: f32,
+    // further events are dropped while this many are still playing
+    pub max_instances: usize,
+    // length of the sound at normal speed in seconds, used to know when an instance is done
+    pub length: f32,
+}
+
+impl SoundEffect {
+    pub const ALL: [SoundEffect; 9] = [
+        SoundEffect::BombPlaced,
+        SoundEffect::FuseTick,
+        SoundEffect::Explosion,
+        SoundEffect::WallBroken,
+        SoundEffect::EnemyKilled,
+        SoundEffect::Pickup,
+        SoundEffect::PlayerHurt,
+        SoundEffect::PlayerDeath,
+        SoundEffect::LevelClear,
+    ];
+
+    pub fn from_event(event: &GameplayEvent) -> SoundEffect {
+        match event {
+            GameplayEvent::BombPlaced { .. } => SoundEffect::BombPlaced,
+            GameplayEvent::FuseTick { .. } => SoundEffect::FuseTick,
+            GameplayEvent::Explosion { .. } => SoundEffect::Explosion,
+            GameplayEvent::WallBroken { .. } => SoundEffect::WallBroken,
+            GameplayEvent::EnemyKilled { .. } => SoundEffect::EnemyKilled,
+            GameplayEvent::PickupCollected { .. } => SoundEffect::Pickup,
+            GameplayEvent::PlayerHurt { .. } => SoundEffect::PlayerHurt,
+            GameplayEvent::PlayerDeath => SoundEffect::PlayerDeath,
+            GameplayEvent::LevelClear => SoundEffect::LevelClear,
+        }
+    }
+
+    pub fn spec(&self) -> SoundSpec {
+        let (file, volume, pitch_variation, max_instances, length) = match self {
+            SoundEffect::BombPlaced => ("sfx/bomb_placed.wav", 0.8, 0.1, 3, 0.12),
+            SoundEffect::FuseTick => ("sfx/fuse_tick.wav", 0.3, 0.05, 2, 0.035),
+            SoundEffect::Explosion => ("sfx/explosion.wav", 1.0, 0.15, 4, 0.6),
+            SoundEffect::WallBroken => ("sfx/wall_broken.wav", 0.6, 0.2, 3, 0.25),
+            SoundEffect::EnemyKilled => ("sfx/enemy_killed.wav", 0.7, 0.1, 3, 0.3),
+            SoundEffect::Pickup => ("sfx/pickup.wav", 0.7, 0.0, 2, 0.24),
+            SoundEffect::PlayerHurt => ("sfx/player_hurt.wav", 0.6, 0.1, 1, 0.15),
+            SoundEffect::PlayerDeath => ("sfx/player_death.wav", 0.9, 0.0, 1, 0.8),
+            SoundEffect::LevelClear => ("sfx/level_clear.wav", 0.8, 0.0, 1, 0.72),
+        };
+
+        SoundSpec { file, volume, pitch_variation, max_instances, length }
+    }
+}
+
+#[derive(Resource)]
+struct SoundEffectHandles(HashMap<SoundEffect, Handle<AudioSource>>);
+
+// when each playing instance of a sound finishes, in seconds since startup
+#[derive(Resource, Default)]
+struct PlayingSounds(HashMap<SoundEffect, Vec<f64>>);
+
+impl PlayingSounds {
+    // forgets finished instances, then makes room for one more if the sound isn't at its cap
+    fn try_start(&mut self, effect: SoundEffect, now: f64, speed: f32) -> bool {
+        let spec = effect.spec();
+        let instances = self.0.entry(effect).or_default();
+        instances.retain(|ends| *ends > now);
+
+        if instances.len() >= spec.max_instances {
+            return false;
+        }
+
+        instances.push(now + (spec.length / speed) as f64);
+        true
+    }
+}
+
+fn load_sound_effects(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let handles = SoundEffect::ALL
+        .iter()
+        .map(|effect| (*effect, asset_server.load(effect.spec().file)))
+        .collect();
+
+    commands.insert_resource(SoundEffectHandles(handles));
+}
+
+fn play_sound_effects_system(
+    mut events: EventReader<GameplayEvent>,
+    audio: Res<Audio>,
+    time: Res<Time>,
+    handles: Res<SoundEffectHandles>,
+    config: Res<GameConfig>,
+    mut playing: ResMut<PlayingSounds>,
+) {
+    let mut rng = rand::thread_rng();
+    let now = time.elapsed_seconds_f64();
+
+    for event in events.iter() {
+        let effect = SoundEffect::from_event(event);
+        let spec = effect.spec();
+
+        let speed = if spec.pitch_variation > 0. {
+            1. + rng.gen_range(-spec.pitch_variation..spec.pitch_variation)
+        } else {
+            1.
+        };
+
+        if !playing.try_start(effect, now, speed) {
+            continue;
+        }
+
+        audio.play_with_settings(
+            handles.0[&effect].clone(),
+            PlaybackSettings {
+                repeat: false,
+                volume: spec.volume * config.sfx_level(),
+                speed,
+            }
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_simultaneous_instances_until_they_finish() {
+        let mut playing = PlayingSounds::default();
+
+        // the hurt sound is capped at one, a second hit while it plays is dropped
+        assert!(playing.try_start(SoundEffect::PlayerHurt, 0., 1.));
+        assert!(!playing.try_start(SoundEffect::PlayerHurt, 0.1, 1.));
+        assert!(playing.try_start(SoundEffect::PlayerHurt, 0.2, 1.));
+
+        // other sounds keep their own count
+        for _ in 0..4 {
+            assert!(playing.try_start(SoundEffect::Explosion, 0.2, 1.));
+        }
+        assert!(!playing.try_start(SoundEffect::Explosion, 0.2, 1.));
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{TILE_SIZE, collision::Collider};
+
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<SpatialIndex>()
+            // after everything in Update has moved, spawned and despawned for the frame
+            .add_system_to_stage(CoreStage::PostUpdate, update_spatial_index);
+    }
+}
+
+type Cell = (i32, i32);
+
+// every entity with a Collider, in a uniform grid with one cell per tile, so collision
+// checks only look at what's nearby.
+// entities are filed by the cell their centre is in, and lookups search one cell further
+// out than asked, so anything up to a tile across is still found when it overhangs a cell
+#[derive(Resource, Default)]
+pub struct SpatialIndex {
+    cells: HashMap<Cell, Vec<Entity>>,
+    entity_cells: HashMap<Entity, Cell>,
+}
+
+impl SpatialIndex {
+    fn cell(position: Vec2) -> Cell {
+        (
+            (position.x / TILE_SIZE).round() as i32,
+            (position.y / TILE_SIZE).round() as i32,
+        )
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        let cell = SpatialIndex::cell(position);
+
+        match self.entity_cells.insert(entity, cell) {
+            Some(old) if old == cell => return,
+            Some(old) => self.remove_from_cell(entity, old),
+            None => (),
+        }
+
+        self.cells.entry(cell).or_default().push(entity);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(cell) = self.entity_cells.remove(&entity) {
+            self.remove_from_cell(entity, cell);
+        }
+    }
+
+    fn remove_from_cell(&mut self, entity: Entity, cell: Cell) {
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.retain(|other| *other != entity);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    // every entity that could overlap a box of the given size centred on the point
+    pub fn near(&self, point: Vec2, size: Vec2) -> impl Iterator<Item = Entity> + '_ {
+        let half = size / 2.;
+        let (min_x, min_y) = SpatialIndex::cell(point - half);
+        let (max_x, max_y) = SpatialIndex::cell(point + half);
+
+        (min_x - 1..=max_x + 1)
+            .flat_map(move |x| (min_y - 1..=max_y + 1).map(move |y| (x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    moved_query: Query<(Entity, &Transform), (With<Collider>, Changed<Transform>)>,
+    removed: RemovedComponents<Collider>,
+) {
+    for entity in removed.iter() {
+        index.remove(entity);
+    }
+
+    for (entity, transform) in moved_query.iter() {
+        index.insert(entity, transform.translation.truncate());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_entities_in_and_around_the_searched_box() {
+        let mut index = SpatialIndex::default();
+        let (near, far, moving) = (Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3));
+
+        index.insert(near, Vec2::new(64., -64.));
+        index.insert(far, Vec2::new(640., -640.));
+        index.insert(moving, Vec2::new(0., 0.));
+
+        let found = |index: &SpatialIndex| {
+            let mut found: Vec<Entity> = index.near(Vec2::new(70., -60.), Vec2::splat(10.)).collect();
+            found.sort();
+            found
+        };
+        assert_eq!(found(&index), vec![near, moving]);
+
+        index.insert(moving, Vec2::new(600., -600.));
+        assert_eq!(found(&index), vec![near]);
+
+        index.remove(near);
+        assert!(found(&index).is_empty());
+        assert_eq!(index.near(Vec2::new(640., -640.), Vec2::ZERO).count(), 2);
+    }
+}
@@ -0,0 +1,350 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+
+use crate::{
+    GameState, MainState, TILE_SIZE, storage,
+    editor::Editor,
+    enemy::EnemyKind,
+    events::GameplayEvent,
+    player::Player,
+    tilemap::Level,
+};
+
+const STATS_FILE: &str = "stats.txt";
+
+// gathers the run's stats from gameplay events into MainState
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<LevelStats>()
+            .add_system_set(SystemSet::on_enter(GameState::Game).with_system(start_level_stats_system))
+            .add_system_set(
+                SystemSet::on_update(GameState::Game)
+                    .with_system(gameplay_stats_system)
+                    .with_system(distance_walked_system)
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::LevelClear).with_system(finish_level_stats_system.label(LevelStatsFinished))
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(finish_level_stats_system.label(LevelStatsFinished))
+            );
+    }
+}
+
+// the level just played has been added to the run's stats, for the screens that show them
+#[derive(SystemLabel)]
+pub struct LevelStatsFinished;
+
+// adds each finished run to the lifetime stats on disk
+pub struct LifetimeStatsPlugin;
+
+impl Plugin for LifetimeStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(LifetimeStats::load())
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(lifetime_stats_system.after(LevelStatsFinished))
+            );
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Stats {
+    pub bombs_placed: usize,
+    pub walls_destroyed: usize,
+    pub bats_killed: usize,
+    pub ogres_killed: usize,
+    pub pickups_collected: usize,
+    pub damage_taken: f32,
+    // in tiles
+    pub distance_walked: f32,
+    // the most bombs that went off in one chain reaction
+    pub longest_chain: usize,
+    // the name of each level played and the seconds spent on it, the last one may not have been cleared
+    pub level_times: Vec<(String, f32)>,
+}
+
+impl Stats {
+    pub fn enemies_killed(&self, kind: EnemyKind) -> usize {
+        match kind {
+            EnemyKind::Bat => self.bats_killed,
+            EnemyKind::Ogre => self.ogres_killed,
+        }
+    }
+
+    pub fn play_time(&self) -> f32 {
+        self.level_times.iter().map(|(_, seconds)| seconds).sum()
+    }
+
+    // one `key value` line per count, and a `level_time seconds name` line per level played.
+    // used by the lifetime stats and save files
+    pub fn write(&self, out: &mut String) {
+        writeln!(out, "bombs_placed {}", self.bombs_placed).unwrap();
+        writeln!(out, "walls_destroyed {}", self.walls_destroyed).unwrap();
+        writeln!(out, "bats_killed {}", self.bats_killed).unwrap();
+        writeln!(out, "ogres_killed {}", self.ogres_killed).unwrap();
+        writeln!(out, "pickups_collected {}", self.pickups_collected).unwrap();
+        writeln!(out, "damage_taken {}", self.damage_taken).unwrap();
+        writeln!(out, "distance_walked {}", self.distance_walked).unwrap();
+        writeln!(out, "longest_chain {}", self.longest_chain).unwrap();
+        for (name, seconds) in self.level_times.iter() {
+            writeln!(out, "level_time {} {}", seconds, name).unwrap();
+        }
+    }
+
+    // reads back one line that write wrote, false if it isn't one
+    pub fn read_line(&mut self, key: &str, value: &str) -> bool {
+        if key == "level_time" {
+            let Some((seconds, name)) = value.split_once(' ') else {
+                return false;
+            };
+            let Ok(seconds) = seconds.parse() else {
+                return false;
+            };
+            self.level_times.push((name.to_string(), seconds));
+            return true;
+        }
+
+        let Ok(number) = value.trim().parse::<f32>() else {
+            return false;
+        };
+        let count = number as usize;
+
+        match key {
+            "bombs_placed" => self.bombs_placed = count,
+            "walls_destroyed" => self.walls_destroyed = count,
+            "bats_killed" => self.bats_killed = count,
+            "ogres_killed" => self.ogres_killed = count,
+            "pickups_collected" => self.pickups_collected = count,
+            "damage_taken" => self.damage_taken = number,
+            "distance_walked" => self.distance_walked = number,
+            "longest_chain" => self.longest_chain = count,
+            _ => return false,
+        }
+        true
+    }
+
+    // for the level clear and game over screens
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "Bombs placed: {}   Walls destroyed: {}", self.bombs_placed, self.walls_destroyed).unwrap();
+        writeln!(
+            out, "Bats killed: {}   Ogres killed: {}",
+            self.enemies_killed(EnemyKind::Bat), self.enemies_killed(EnemyKind::Ogre),
+        ).unwrap();
+        writeln!(out, "Pickups: {}   Damage taken: {:.0}", self.pickups_collected, self.damage_taken).unwrap();
+        writeln!(out, "Distance walked: {:.0} tiles   Longest chain: {}", self.distance_walked, self.longest_chain).unwrap();
+        for (name, seconds) in self.level_times.iter() {
+            writeln!(out, "{}: {}", name, clock(*seconds)).unwrap();
+        }
+
+        out
+    }
+}
+
+fn clock(seconds: f32) -> String {
+    let seconds = seconds.round() as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// every run added together, stored one `key value` per line
+#[derive(Resource, Clone, PartialEq, Debug, Default)]
+pub struct LifetimeStats {
+    pub runs: usize,
+    pub levels_cleared: usize,
+    pub best_score: usize,
+    // in seconds
+    pub play_time: f32,
+    // level_times is left empty, play_time covers it
+    pub totals: Stats,
+}
+
+impl LifetimeStats {
+    pub fn load() -> Self {
+        match storage::read(STATS_FILE) {
+            Ok(Some(contents)) => LifetimeStats::parse(&contents),
+            Ok(None) => LifetimeStats::default(),
+            Err(error) => {
+                warn!("could not read lifetime stats: {}", error);
+                LifetimeStats::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::write(STATS_FILE, &self.to_file_string()) {
+            warn!("could not save lifetime stats: {}", error);
+        }
+    }
+
+    // unknown keys and values that don't parse are skipped
+    pub fn parse(contents: &str) -> Self {
+        let mut stats = LifetimeStats::default();
+
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            let Ok(number) = value.trim().parse::<f32>() else {
+                continue;
+            };
+            let count = number as usize;
+
+            match key {
+                "runs" => stats.runs = count,
+                "levels_cleared" => stats.levels_cleared = count,
+                "best_score" => stats.best_score = count,
+                "play_time" => stats.play_time = number,
+                _ => {
+                    stats.totals.read_line(key, value);
+                },
+            }
+        }
+
+        stats
+    }
+
+    pub fn to_file_string(&self) -> String {
+        let mut out = String::new();
+
+        writeln!(out, "runs {}", self.runs).unwrap();
+        writeln!(out, "levels_cleared {}", self.levels_cleared).unwrap();
+        writeln!(out, "best_score {}", self.best_score).unwrap();
+        writeln!(out, "play_time {}", self.play_time).unwrap();
+        self.totals.write(&mut out);
+
+        out
+    }
+
+    // levels_cleared counts every level of the run but the one it ended on
+    pub fn add_run(&mut self, run: &Stats, score: usize) {
+        let totals = &mut self.totals;
+
+        self.runs += 1;
+        self.levels_cleared += run.level_times.len().saturating_sub(1);
+        self.best_score = self.best_score.max(score);
+        self.play_time += run.play_time();
+        totals.bombs_placed += run.bombs_placed;
+        totals.walls_destroyed += run.walls_destroyed;
+        totals.bats_killed += run.bats_killed;
+        totals.ogres_killed += run.ogres_killed;
+        totals.pickups_collected += run.pickups_collected;
+        totals.damage_taken += run.damage_taken;
+        totals.distance_walked += run.distance_walked;
+        totals.longest_chain = totals.longest_chain.max(run.longest_chain);
+    }
+}
+
+// the time on the level so far, and where the player was last frame
+#[derive(Resource, Default)]
+pub struct LevelStats {
+    pub time: f32,
+    last_position: Option<Vec2>,
+}
+
+fn start_level_stats_system(mut level_stats: ResMut<LevelStats>) {
+    *level_stats = LevelStats::default();
+}
+
+fn gameplay_stats_system(
+    time: Res<Time>,
+    mut events: EventReader<GameplayEvent>,
+    mut main_state: ResMut<MainState>,
+    mut level_stats: ResMut<LevelStats>,
+) {
+    level_stats.time += time.delta_seconds();
+
+    for event in events.iter() {
+        let stats = &mut main_state.stats;
+        match event {
+            GameplayEvent::BombPlaced { .. } => stats.bombs_placed += 1,
+            GameplayEvent::WallBroken { .. } => stats.walls_destroyed += 1,
+            GameplayEvent::EnemyKilled { kind: EnemyKind::Bat, .. } => stats.bats_killed += 1,
+            GameplayEvent::EnemyKilled { kind: EnemyKind::Ogre, .. } => stats.ogres_killed += 1,
+            GameplayEvent::PickupCollected { .. } => stats.pickups_collected += 1,
+            GameplayEvent::PlayerHurt { damage } => stats.damage_taken += damage,
+            // a bomb going off on its own is a chain of one
+            GameplayEvent::Explosion { chain, .. } => stats.longest_chain = stats.longest_chain.max(chain + 1),
+            _ => {},
+        }
+    }
+}
+
+fn distance_walked_system(
+    player_query: Query<&Player>,
+    mut main_state: ResMut<MainState>,
+    mut level_stats: ResMut<LevelStats>,
+) {
+    let Ok(player) = player_query.get_single() else {
+        return;
+    };
+
+    if let Some(last) = level_stats.last_position.replace(player.position) {
+        let step = last.distance(player.position);
+        // respawning and loading a save jump the player further than a frame's walk
+        if step > 0. && step < TILE_SIZE {
+            main_state.stats.distance_walked += step / TILE_SIZE;
+        }
+    }
+}
+
+fn finish_level_stats_system(
+    level: Res<Level>,
+    level_stats: Res<LevelStats>,
+    mut main_state: ResMut<MainState>,
+) {
+    main_state.stats.level_times.push((level.name.clone(), level_stats.time));
+}
+
+// play tests from the editor don't count
+fn lifetime_stats_system(
+    main_state: Res<MainState>,
+    editor: Option<Res<Editor>>,
+    mut lifetime: ResMut<LifetimeStats>,
+) {
+    if editor.is_some_and(|editor| editor.play_testing) {
+        return;
+    }
+
+    lifetime.add_run(&main_state.stats, main_state.score);
+    lifetime.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_add_up_into_lifetime_stats_that_round_trip() {
+        let run = Stats {
+            bombs_placed: 12,
+            walls_destroyed: 30,
+            bats_killed: 4,
+            ogres_killed: 1,
+            pickups_collected: 3,
+            damage_taken: 150.,
+            distance_walked: 210.5,
+            longest_chain: 3,
+            level_times: vec![("01-warm-up".to_string(), 95.), ("02-fortress".to_string(), 40.5)],
+        };
+
+        let mut lifetime = LifetimeStats::default();
+        lifetime.add_run(&run, 2400);
+        lifetime.add_run(&Stats { longest_chain: 2, ..run.clone() }, 900);
+
+        assert_eq!(lifetime.runs, 2);
+        assert_eq!(lifetime.levels_cleared, 2);
+        assert_eq!(lifetime.best_score, 2400);
+        assert_eq!(lifetime.totals.enemies_killed(EnemyKind::Bat), 8);
+        assert_eq!(lifetime.totals.longest_chain, 3);
+        assert_eq!(lifetime.play_time, 271.);
+
+        assert_eq!(LifetimeStats::parse(&lifetime.to_file_string()), lifetime);
+        assert!(run.report().contains("02-fortress: 0:41"));
+    }
+}
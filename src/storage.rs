@@ -0,0 +1,48 @@
+use std::{
+    env,
+    fs,
+    io,
+    path::PathBuf,
+};
+
+const APP_DIR: &str = "bomberman";
+
+// the per-user directory that high scores and other persisted data live in.
+// BOMBERMAN_DATA_DIR overrides it, which is handy for tests and portable installs
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("BOMBERMAN_DATA_DIR") {
+        return PathBuf::from(dir);
+    }
+
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+
+    base.unwrap_or_else(|| PathBuf::from(".")).join(APP_DIR)
+}
+
+// reads a file from the data directory, a missing file reads as None
+pub fn read(file_name: &str) -> io::Result<Option<String>> {
+    match fs::read_to_string(data_dir().join(file_name)) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+// writes to a temporary file first and renames it over the old one,
+// so a crash halfway through never leaves a truncated file behind
+pub fn write(file_name: &str, contents: &str) -> io::Result<()> {
+    let dir = data_dir();
+    fs::create_dir_all(&dir)?;
+
+    let temp_path = dir.join(format!("{}.tmp", file_name));
+    fs::write(&temp_path, contents)?;
+    fs::rename(temp_path, dir.join(file_name))
+}
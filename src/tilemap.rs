@@ -1,10 +1,16 @@
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, },
+    fs,
+    io,
+    path::{Path, PathBuf},
 };
 use bevy::{prelude::*, };
 
-use crate::{GameTextures, TILE_SIZE, GameState};
+use crate::{GameTextures, TILE_SIZE, GameState, player::{self, Player, PowerUp}, enemy::Enemy, events::GameplayEvent, collision::{Collider, Layer}};
+
+// how long a level lasts when its file doesn't say
+pub const DEFAULT_TIME_LIMIT: u32 = 200;
+
+pub const EXIT_COLOR: Color = Color::rgb(1.0, 0.8, 0.2);
 
 pub struct TileMapPlugin;
 
@@ -12,75 +18,349 @@ pub struct TileMapPlugin;
 struct Map;
 
 #[derive(Component)]
-pub struct TileCollider;
+pub struct Breakable;
+
+// a breakable wall with the exit underneath it
+#[derive(Component)]
+pub struct HidesExit;
 
+// a breakable wall with a pickup underneath it
 #[derive(Component)]
-pub struct Breakable;
+pub struct HidesPickup(pub PowerUp);
+
+#[derive(Component)]
+pub struct Exit;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tile {
+    Wall,
+    Floor,
+    Breakable,
+    Exit,
+    HiddenExit,
+}
+
+impl Tile {
+    pub fn glyph(&self) -> char {
+        match self {
+            Tile::Wall => '#',
+            Tile::Floor => '.',
+            Tile::Breakable => '@',
+            Tile::Exit => 'X',
+            Tile::HiddenExit => 'x',
+        }
+    }
+
+    pub fn is_breakable(&self) -> bool {
+        matches!(self, Tile::Breakable | Tile::HiddenExit)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameMode {
+    // clear the level by killing every enemy and reaching the exit
+    #[default]
+    Story,
+    // an arena, when time runs out it closes in from the edges
+    Battle,
+}
+
+// the level that create_map_system builds, parsed from the ascii map format:
+// '#' wall, '.' floor, '@' breakable wall, 'X' exit, 'x' exit hidden under a breakable wall,
+// 'P' player spawn, 'E' enemy spawn, 'B' an extra bomb pickup, 'b' the same pickup hidden under
+// a breakable wall. lines of the form `key=value` before the grid set the
+// time limit in seconds (`time=200`) and the mode (`mode=battle`)
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct Level {
+    pub name: String,
+    pub tiles: Vec<Vec<Tile>>,
+    pub player_spawn: (usize, usize),
+    pub enemy_spawns: Vec<(usize, usize)>,
+    // pickups lying on the floor when the level starts, or hidden under breakable walls
+    pub pickups: Vec<((usize, usize), PowerUp)>,
+    pub time_limit: u32,
+    pub mode: GameMode,
+    // the map file it was loaded from, None for generated levels and saved games
+    pub path: Option<PathBuf>,
+}
+
+impl Level {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Level> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        let name = path.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Ok(Level { path: Some(path.to_path_buf()), ..Level::parse(&name, &source) })
+    }
+
+    pub fn parse(name: &str, source: &str) -> Level {
+        let mut tiles = Vec::new();
+        let mut player_spawn = None;
+        let mut enemy_spawns = Vec::new();
+        let mut pickups = Vec::new();
+        let mut time_limit = DEFAULT_TIME_LIMIT;
+        let mut mode = GameMode::Story;
+
+        let mut lines = source.lines().peekable();
+        while let Some((key, value)) = lines.peek().and_then(|line| line.split_once('=')) {
+            match (key.trim(), value.trim()) {
+                ("time", seconds) => time_limit = seconds.parse().unwrap_or(DEFAULT_TIME_LIMIT),
+                ("mode", "battle") => mode = GameMode::Battle,
+                _ => {},
+            }
+            lines.next();
+        }
+
+        for (y, line) in lines.enumerate() {
+            let mut row = Vec::new();
+
+            for (x, char) in line.chars().enumerate() {
+                let tile = match char {
+                    '#' => Tile::Wall,
+                    '.' => Tile::Floor,
+                    '@' => Tile::Breakable,
+                    'X' => Tile::Exit,
+                    'x' => Tile::HiddenExit,
+                    'P' => {
+                        player_spawn = Some((x, y));
+                        Tile::Floor
+                    },
+                    'E' => {
+                        enemy_spawns.push((x, y));
+                        Tile::Floor
+                    },
+                    'B' | 'b' => {
+                        pickups.push(((x, y), PowerUp::ExtraBomb));
+                        if char.is_lowercase() { Tile::Breakable } else { Tile::Floor }
+                    },
+                    _ => Tile::Wall,
+                };
+                row.push(tile);
+            }
+
+            tiles.push(row);
+        }
+
+        Level {
+            name: name.to_string(),
+            tiles,
+            player_spawn: player_spawn.unwrap_or((1, 1)),
+            enemy_spawns,
+            pickups,
+            time_limit,
+            mode,
+            path: None,
+        }
+    }
+
+
+    // writes the level back out in the same format parse reads
+    pub fn to_map_string(&self) -> String {
+        let mut rows: Vec<Vec<char>> = self.tiles
+            .iter()
+            .map(|row| row.iter().map(Tile::glyph).collect())
+            .collect();
+
+        let mut mark = |(x, y): (usize, usize), glyph: char| {
+            if let Some(cell) = rows.get_mut(y).and_then(|row| row.get_mut(x)) {
+                *cell = glyph;
+            }
+        };
+
+        mark(self.player_spawn, 'P');
+        for spawn in self.enemy_spawns.iter() {
+            mark(*spawn, 'E');
+        }
+        for ((x, y), power_up) in self.pickups.iter() {
+            let glyph = match power_up {
+                PowerUp::ExtraBomb => 'B',
+            };
+            let hidden = self.tiles.get(*y).and_then(|row| row.get(*x)) == Some(&Tile::Breakable);
+            mark((*x, *y), if hidden { glyph.to_ascii_lowercase() } else { glyph });
+        }
+
+        let mut lines = vec![format!("time={}", self.time_limit)];
+        if self.mode == GameMode::Battle {
+            lines.push("mode=battle".to_string());
+        }
+        lines.extend(rows.iter().map(|row| row.iter().collect::<String>()));
+
+        lines.join("\n")
+    }
+
+    pub fn width(&self) -> usize {
+        self.tiles.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.tiles.len()
+    }
+
+    // every open tile, furthest from the given tile first
+    pub fn farthest_open_tiles(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut tiles: Vec<(usize, usize)> = self.tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, tile)| (x, y, *tile)))
+            .filter(|(_, _, tile)| matches!(tile, Tile::Floor | Tile::Exit))
+            .map(|(x, y, _)| (x, y))
+            .collect();
+
+        let distance = |(x, y): (usize, usize)| x.abs_diff(from.0) + y.abs_diff(from.1);
+        tiles.sort_by_key(|tile| std::cmp::Reverse(distance(*tile)));
+        tiles
+    }
+}
+
+// world position of the centre of a tile
+pub fn tile_to_world(x: usize, y: usize) -> Vec2 {
+    Vec2::new(x as f32 * TILE_SIZE, -(y as f32) * TILE_SIZE)
+}
+
+// the tile a world position falls in
+pub fn world_to_tile(position: Vec2) -> (usize, usize) {
+    (
+        (position.x / TILE_SIZE).round().max(0.) as usize,
+        (-position.y / TILE_SIZE).round().max(0.) as usize,
+    )
+}
 
 impl Plugin for TileMapPlugin {
     fn build(&self, app: &mut App) {
        app
-        .add_system_set(SystemSet::on_enter(GameState::Game).with_system(create_map_system));
+        .add_system_set(SystemSet::on_enter(GameState::Game).with_system(create_map_system))
+        .add_system_set(SystemSet::on_update(GameState::Game).with_system(level_clear_system));
     }
 }
 
 fn create_map_system(
     mut commands: Commands,
     game_textures: Res<GameTextures>,
+    level: Res<Level>,
 ) {
-    let file = File::open("assets/map.txt").expect("no map file");
+    for ((x, y), power_up) in level.pickups.iter() {
+        if !level.tiles[*y][*x].is_breakable() {
+            player::spawn_pickup(&mut commands, &game_textures, tile_to_world(*x, *y), *power_up);
+        }
+    }
 
-    for (y, line) in BufReader::new(file).lines().enumerate() {
-        if let Ok(line) = line {
-            for (x, char) in line.chars().enumerate() {
+    for (y, row) in level.tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            let position = tile_to_world(x, y);
 
-                let (texture, index_z) = match char {
-                   '#' => {(game_textures.wall.clone(), 100.0)},
-                   '.' => {(game_textures.floor.clone(), 1.0)},
-                   '@' => {(game_textures.breakable_wall.clone(), 100.0)},
-                    _ => {(game_textures.wall.clone(), 100.0)},
-                };
+            let (texture, index_z) = match tile {
+                Tile::Wall => {(game_textures.wall.clone(), 100.0)},
+                Tile::Floor | Tile::Exit => {(game_textures.floor.clone(), 1.0)},
+                Tile::Breakable | Tile::HiddenExit => {(game_textures.breakable_wall.clone(), 100.0)},
+            };
+
+            if *tile == Tile::Exit {
+                spawn_exit(&mut commands, &game_textures, position);
+            }
+
+            // spawn a tile
+            let mut tile_entity = commands.spawn(SpriteBundle {
+                texture,
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                    ..Default::default()
+                },
+                transform: Transform {
+                    translation: position.extend(index_z),
+                    ..Default::default()
+                },
+                ..Default::default()
+                },
+            );
+            tile_entity.insert(Map);
 
-                // spawn a tile
-                let mut tile = commands.spawn(SpriteBundle {
-                    texture: texture,
+            if *tile == Tile::Wall {
+                tile_entity.insert(Collider::new(Vec2::splat(TILE_SIZE), Layer::Wall));
+            }
+
+            if tile.is_breakable() {
+                tile_entity.insert(Collider::new(Vec2::splat(TILE_SIZE), Layer::Breakable));
+                tile_entity.insert(Breakable);
+
+                if *tile == Tile::HiddenExit {
+                    tile_entity.insert(HidesExit);
+                } else if let Some((_, power_up)) = level.pickups.iter().find(|(at, _)| *at == (x, y)) {
+                    tile_entity.insert(HidesPickup(*power_up));
+                }
+
+                // spawn a floor tile under breakable walls
+                commands.spawn(SpriteBundle {
+                    texture: game_textures.floor.clone(),
                     sprite: Sprite {
                         custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
                         ..Default::default()
                     },
-                    transform: Transform { 
-                        translation: Vec3::new(x as f32 * TILE_SIZE , -(y as f32) * TILE_SIZE, index_z), 
+                    transform: Transform {
+                        translation: position.extend(1.),
                         ..Default::default()
                     },
                     ..Default::default()
                     },
-                );
-
-                if char == '#' {
-                    tile.insert(TileCollider);
-                }
-
-                if char == '@' {
-                    tile.insert(TileCollider);
-                    tile.insert(Breakable);
-
-                    // spawn a floor tile under breakable walls
-                    commands.spawn(SpriteBundle {
-                        texture: game_textures.floor.clone(),
-                        sprite: Sprite {
-                            custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
-                            ..Default::default()
-                        },
-                        transform: Transform { 
-                            translation: Vec3::new(x as f32 * TILE_SIZE , -(y as f32) * TILE_SIZE, 1.), 
-                            ..Default::default()
-                        },
-                        ..Default::default()
-                        },
-                    );
+                )
+                .insert(Map);
 
-                }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// the exit is a floor tile with a tint, drawn just above the floor
+pub fn spawn_exit(
+    commands: &mut Commands,
+    game_textures: &GameTextures,
+    position: Vec2,
+) {
+    commands.spawn(SpriteBundle {
+        texture: game_textures.floor.clone(),
+        sprite: Sprite {
+            custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+            color: EXIT_COLOR,
+            ..Default::default()
+        },
+        transform: Transform {
+            translation: position.extend(2.),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .insert(Map)
+    .insert(Exit);
+}
+
+// a story level is cleared by standing on the exit once every enemy is dead
+pub fn level_clear_system(
+    level: Res<Level>,
+    player_query: Query<&Player>,
+    enemy_query: Query<(), With<Enemy>>,
+    exit_query: Query<&Transform, With<Exit>>,
+    mut game_state: ResMut<State<GameState>>,
+    mut events: EventWriter<GameplayEvent>,
+) {
+    if level.mode != GameMode::Story || !enemy_query.is_empty() {
+        return;
+    }
+
+    // a player dying on the exit doesn't clear it
+    let player = player_query.single();
+    if player.health <= 0. {
+        return;
+    }
+
+    let player_tile = world_to_tile(player.position);
+    let on_exit = exit_query
+        .iter()
+        .any(|transform| world_to_tile(transform.translation.truncate()) == player_tile);
+
+    if on_exit {
+        events.send(GameplayEvent::LevelClear);
+        // other Game systems may have queued a transition this frame, like Esc ending a play test
+        game_state.overwrite_set(GameState::LevelClear).unwrap();
+    }
+}
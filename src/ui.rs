@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+
+use crate::GameTextures;
+
+// the root node of a full window menu screen, despawned by despawn_screen
+#[derive(Component)]
+pub struct Screen;
+
+pub fn screen_root(commands: &mut Commands) -> Entity {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Screen)
+        .id()
+}
+
+pub fn text_style(game_textures: &GameTextures, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: game_textures.font.clone(),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    }
+}
+
+pub fn despawn_screen(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<Screen>>,
+) {
+    for entity in screen_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,179 @@
+use std::{fmt, fs};
+
+// every glyph the map format knows, see Level::parse
+const GLYPHS: &str = "#.@XxPEBb";
+
+// a problem with a map, at a 1 based line and column of the file
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MapError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+// `bomberman validate <map>`, checks a map without opening a window and returns the exit code
+pub fn run(args: &[String]) -> i32 {
+    let [path] = args else {
+        eprintln!("usage: bomberman validate <map>");
+        return 2;
+    };
+
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            return 2;
+        },
+    };
+
+    let errors = validate(&source);
+    if errors.is_empty() {
+        println!("{}: ok", path);
+        return 0;
+    }
+
+    for error in errors.iter() {
+        eprintln!("{}:{}", path, error);
+    }
+    1
+}
+
+// everything wrong with a map, in the order it appears in the file. Level::parse accepts
+// anything, so this is what catches typos before they turn into walls
+pub fn validate(source: &str) -> Vec<MapError> {
+    let mut errors = Vec::new();
+    let mut error = |line: usize, column: usize, message: String| {
+        errors.push(MapError { line: line + 1, column: column + 1, message });
+    };
+
+    let lines: Vec<&str> = source.lines().collect();
+    let header = lines.iter().take_while(|line| line.contains('=')).count();
+    let battle = lines[..header].iter().any(|line| line.replace(' ', "") == "mode=battle");
+
+    // the grid with the file line each row came from
+    let rows: Vec<(usize, Vec<char>)> = lines
+        .iter()
+        .enumerate()
+        .skip(header)
+        .map(|(line, row)| (line, row.chars().collect()))
+        .collect();
+
+    let Some((first_line, first_row)) = rows.first() else {
+        error(header, 0, "the map has no grid".to_string());
+        return errors;
+    };
+    let width = first_row.len();
+    let last_line = rows.last().map(|(line, _)| *line).unwrap_or(*first_line);
+
+    let mut spawns = Vec::new();
+    let mut has_exit = false;
+
+    for (y, (line, row)) in rows.iter().enumerate() {
+        if row.len() != width {
+            error(*line, row.len().min(width), format!("row is {} tiles wide, the first row is {}", row.len(), width));
+        }
+
+        for (x, glyph) in row.iter().enumerate() {
+            if !GLYPHS.contains(*glyph) {
+                error(*line, x, format!("unknown tile '{}'", glyph));
+                continue;
+            }
+
+            let edge = x == 0 || y == 0 || x + 1 == row.len() || *line == last_line;
+            if edge && *glyph != '#' {
+                error(*line, x, format!("the outer wall is open, found '{}'", glyph));
+            }
+
+            match glyph {
+                'P' => spawns.push((x, y)),
+                'X' | 'x' => has_exit = true,
+                _ => {},
+            }
+        }
+    }
+
+    match spawns.as_slice() {
+        [] => error(*first_line, 0, "there is no player spawn".to_string()),
+        [_] => {},
+        [_, extra @ ..] => {
+            for (x, y) in extra {
+                error(rows[*y].0, *x, "there is more than one player spawn".to_string());
+            }
+        },
+    }
+
+    if !has_exit && !battle {
+        error(*first_line, 0, "a story level needs an exit".to_string());
+    }
+
+    // everything that isn't a wall should be reachable, breakable walls can be blown open
+    let walkable = |(x, y): (usize, usize)| {
+        rows.get(y)
+            .and_then(|(_, row)| row.get(x))
+            .is_some_and(|glyph| *glyph != '#' && GLYPHS.contains(*glyph))
+    };
+
+    if let Some(&spawn) = spawns.first() {
+        // one flag per glyph, rows can be ragged
+        let mut reached: Vec<Vec<bool>> = rows.iter().map(|(_, row)| vec![false; row.len()]).collect();
+        reached[spawn.1][spawn.0] = true;
+        let mut frontier = vec![spawn];
+        while let Some((x, y)) = frontier.pop() {
+            let neighbours = [(x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1)];
+            for next in neighbours {
+                if walkable(next) && !reached[next.1][next.0] {
+                    reached[next.1][next.0] = true;
+                    frontier.push(next);
+                }
+            }
+        }
+
+        for (y, (line, row)) in rows.iter().enumerate() {
+            for (x, glyph) in row.iter().enumerate() {
+                if walkable((x, y)) && !reached[y][x] {
+                    error(*line, x, format!("'{}' can't be reached from the player spawn", glyph));
+                }
+            }
+        }
+    }
+
+    errors.sort_by_key(|error| (error.line, error.column));
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_each_problem_at_its_line_and_column() {
+        assert!(validate(&std::fs::read_to_string("assets/map.txt").unwrap()).is_empty());
+        assert!(validate("mode=battle\n#####\n#P.@#\n#####").is_empty());
+
+        let errors: Vec<String> = validate("\
+time=100
+#####
+#P#.#
+#.#?#
+#.P..
+####")
+            .iter()
+            .map(MapError::to_string)
+            .collect();
+
+        assert_eq!(errors, [
+            "2:1: a story level needs an exit",
+            "3:4: '.' can't be reached from the player spawn",
+            "4:4: unknown tile '?'",
+            "5:3: there is more than one player spawn",
+            "5:5: the outer wall is open, found '.'",
+            "6:5: row is 4 tiles wide, the first row is 5",
+        ]);
+    }
+}